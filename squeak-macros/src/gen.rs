@@ -1,4 +1,6 @@
+use convert_case::{Case, Casing};
 use quote::{format_ident, quote, TokenStreamExt};
+use syn::{Field, Ident};
 
 use super::Table;
 
@@ -7,14 +9,50 @@ pub(crate) fn gen_table_impls(table: Table) -> proc_macro2::TokenStream {
         ident,
         schema_type,
         name,
+        without_row_id,
         pk_field,
         row_id_field,
+        row_id_column,
+        autoindex_fields,
+        desc_fields,
+        column_count,
     } = table;
 
-    let row_id_fn = if let Some(row_id_field) = row_id_field {
+    if without_row_id {
+        let pk_field = pk_field.expect("WITHOUT ROWID tables must have a primary key");
+        assert_eq!(
+            autoindex_fields.len(),
+            1,
+            "unique columns are not supported on WITHOUT ROWID tables"
+        );
+        let pk_field_ident = pk_field.ident.as_ref().unwrap();
+        let (pk_field_ty, pk_sorted_field) =
+            sorted_field(&pk_field, desc_fields.contains(pk_field_ident));
+
+        return quote!(
+            impl Table for #ident {
+                const TYPE: SchemaType = SchemaType::#schema_type;
+                const NAME: &'static str = #name;
+                const COLUMN_COUNT: Option<usize> = Some(#column_count);
+            }
+
+            impl WithoutRowId for #ident {
+                type SortedFields = (#pk_field_ty,);
+
+                fn into_sorted_fields(self) -> Self::SortedFields {
+                    (#pk_sorted_field,)
+                }
+            }
+        );
+    }
+
+    let row_id_fn = if let (Some(row_id_field), Some(row_id_column)) = (row_id_field, row_id_column)
+    {
         let row_id_ident = row_id_field.ident.as_ref().unwrap();
         Some(quote!(
-            fn deserialize_row_id(&mut self, row_id: u64) {
+            const ROW_ID_COLUMN: Option<usize> = Some(#row_id_column);
+
+            fn deserialize_row_id(&mut self, row_id: i64) {
                 self.#row_id_ident = row_id;
             }
         ))
@@ -26,6 +64,7 @@ pub(crate) fn gen_table_impls(table: Table) -> proc_macro2::TokenStream {
         impl Table for #ident {
             const TYPE: SchemaType = SchemaType::#schema_type;
             const NAME: &'static str = #name;
+            const COLUMN_COUNT: Option<usize> = Some(#column_count);
         }
 
         impl WithRowId for #ident {
@@ -33,39 +72,84 @@ pub(crate) fn gen_table_impls(table: Table) -> proc_macro2::TokenStream {
         }
     );
 
-    if let Some(pk_field) = pk_field {
-        let pk_index_ident = format_ident!("{}PK", ident);
-        let pk_index_name = format!("sqlite_autoindex_{}_1", name);
-        let pk_field_ident = pk_field.ident.as_ref().unwrap();
-        let pk_field_ty = &pk_field.ty;
+    // SQLite numbers autoindexes in the order their constraints are declared.
+    for (number, field) in autoindex_fields.iter().enumerate() {
+        let field_ident = field.ident.as_ref().unwrap();
+        let index_ident = if pk_field.as_ref().and_then(|pk| pk.ident.as_ref()) == Some(field_ident)
+        {
+            format_ident!("{}PK", ident)
+        } else {
+            format_ident!(
+                "{}{}Unique",
+                ident,
+                field_ident.to_string().to_case(Case::Pascal)
+            )
+        };
+        let index_name = format!("sqlite_autoindex_{}_{}", name, number + 1);
 
-        result.append_all(quote!(
-            #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
-            struct #pk_index_ident {
-                #pk_field_ident: #pk_field_ty,
-                key: u64,
-            }
+        let desc = desc_fields.contains(field_ident);
+        result.append_all(gen_autoindex(
+            &ident,
+            &index_ident,
+            &index_name,
+            field,
+            desc,
+        ));
+    }
 
-            impl Table for #pk_index_ident {
-                const TYPE: SchemaType = SchemaType::Index;
-                const NAME: &'static str = #pk_index_name;
-            }
+    result
+}
 
-            impl WithoutRowId for #pk_index_ident {
-                type SortedFields = (#pk_field_ty,);
+fn gen_autoindex(
+    table_ident: &Ident,
+    index_ident: &Ident,
+    index_name: &str,
+    field: &Field,
+    desc: bool,
+) -> proc_macro2::TokenStream {
+    let field_ident = field.ident.as_ref().unwrap();
+    let field_ty = &field.ty;
+    let (sorted_ty, sorted_field) = sorted_field(field, desc);
 
-                fn into_sorted_fields(self) -> Self::SortedFields {
-                    (self.#pk_field_ident,)
-                }
+    quote!(
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+        struct #index_ident {
+            #field_ident: #field_ty,
+            key: i64,
+        }
+
+        impl Table for #index_ident {
+            const TYPE: SchemaType = SchemaType::Index;
+            const NAME: &'static str = #index_name;
+        }
+
+        impl WithoutRowId for #index_ident {
+            type SortedFields = (#sorted_ty,);
+
+            fn into_sorted_fields(self) -> Self::SortedFields {
+                (#sorted_field,)
             }
+        }
 
-            impl Index<#ident> for #pk_index_ident {
-                fn get_row_id(&self) -> u64 {
-                    self.key
-                }
+        impl Index<#table_ident> for #index_ident {
+            fn get_row_id(&self) -> i64 {
+                self.key
             }
-        ));
-    }
+        }
+    )
+}
 
-    result
+/// The type and value of a key field as it is sorted in the index. Fields
+/// stored in descending order are wrapped in `Reverse`.
+fn sorted_field(field: &Field, desc: bool) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let field_ident = field.ident.as_ref().unwrap();
+    let field_ty = &field.ty;
+    if desc {
+        (
+            quote!(::std::cmp::Reverse<#field_ty>),
+            quote!(::std::cmp::Reverse(self.#field_ident)),
+        )
+    } else {
+        (quote!(#field_ty), quote!(self.#field_ident))
+    }
 }
@@ -8,8 +8,16 @@ struct Table {
     ident: Ident,
     schema_type: Ident,
     name: String,
+    without_row_id: bool,
     pk_field: Option<Field>,
     row_id_field: Option<Field>,
+    // The position of the row id field, which is also its column index.
+    row_id_column: Option<usize>,
+    // Fields that SQLite creates an autoindex for, in declaration order.
+    autoindex_fields: Vec<Field>,
+    // Key fields stored in descending order, as with `PRIMARY KEY DESC`.
+    desc_fields: Vec<Ident>,
+    column_count: usize,
 }
 
 #[proc_macro_derive(Table, attributes(table))]
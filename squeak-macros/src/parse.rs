@@ -16,24 +16,40 @@ pub(crate) fn parse_input(input: DeriveInput) -> Table {
     let schema_type = format_ident!("Table");
     let default_name = ident.to_string().to_case(Case::Snake);
 
-    let name = parse_struct_attrs(input.attrs).unwrap_or(default_name);
-    let (pk_field, row_id_field) = parse_fields(fields);
+    let (name, without_row_id) = parse_struct_attrs(input.attrs);
+    let name = name.unwrap_or(default_name);
+    let column_count = fields.named.len();
+    let (pk_field, row_id_field, row_id_column, autoindex_fields, desc_fields) =
+        parse_fields(fields);
 
     Table {
         ident,
         schema_type,
         name,
+        without_row_id,
         pk_field,
         row_id_field,
+        row_id_column,
+        autoindex_fields,
+        desc_fields,
+        column_count,
     }
 }
 
-fn parse_struct_attrs(attrs: Vec<Attribute>) -> Option<String> {
+fn parse_struct_attrs(attrs: Vec<Attribute>) -> (Option<String>, bool) {
     let mut name = None;
+    let mut without_row_id = false;
 
     for attr in attrs {
         if into_ident(attr.path()) == "table" {
             let arg = attr.parse_args::<Expr>().unwrap();
+            if let Expr::Path(path) = &arg {
+                match into_ident(&path.path).to_string().as_str() {
+                    "without_rowid" => without_row_id = true,
+                    _ => unimplemented!("unknown attribute"),
+                }
+                continue;
+            }
             let Expr::Assign(assign) = arg else {
                 unimplemented!("non-assign attribute");
             };
@@ -55,23 +71,42 @@ fn parse_struct_attrs(attrs: Vec<Attribute>) -> Option<String> {
         }
     }
 
-    name
+    (name, without_row_id)
 }
 
-fn parse_fields(fields: FieldsNamed) -> (Option<Field>, Option<Field>) {
+type ParsedFields = (
+    Option<Field>,
+    Option<Field>,
+    Option<usize>,
+    Vec<Field>,
+    Vec<Ident>,
+);
+
+fn parse_fields(fields: FieldsNamed) -> ParsedFields {
     let mut pk_field = None;
     let mut row_id_field = None;
+    let mut row_id_column = None;
+    let mut autoindex_fields = Vec::new();
+    let mut desc_fields = Vec::new();
 
-    for field in fields.named {
+    for (column, field) in fields.named.into_iter().enumerate() {
         for attr in &field.attrs {
             if into_ident(attr.path()) == "table" {
                 let arg = attr.parse_args::<Path>().unwrap();
                 match into_ident(&arg).to_string().as_str() {
                     "primary_key" => {
                         pk_field = Some(field.clone());
+                        autoindex_fields.push(field.clone());
+                    }
+                    "unique" => {
+                        autoindex_fields.push(field.clone());
                     }
                     "row_id" => {
                         row_id_field = Some(field.clone());
+                        row_id_column = Some(column);
+                    }
+                    "desc" => {
+                        desc_fields.push(field.ident.clone().unwrap());
                     }
                     _ => unimplemented!("unknown attribute"),
                 }
@@ -79,7 +114,13 @@ fn parse_fields(fields: FieldsNamed) -> (Option<Field>, Option<Field>) {
         }
     }
 
-    (pk_field, row_id_field)
+    (
+        pk_field,
+        row_id_field,
+        row_id_column,
+        autoindex_fields,
+        desc_fields,
+    )
 }
 
 fn into_ident(path: &Path) -> Ident {
@@ -1,2 +1,40 @@
+//! Reads SQLite3 databases into Rust structs.
+//!
+//! Tables are described by deriving [`Table`](schema::Table) alongside serde's
+//! `Deserialize`, then read through a [`TableHandle`](schema::TableHandle):
+//!
+//! ```
+//! use anyhow::Result;
+//! use serde::Deserialize;
+//! use squeak::{
+//!     physical::db::DB,
+//!     schema::{SchemaType, Table, WithRowId},
+//! };
+//! use squeak_macros::Table;
+//!
+//! #[derive(Debug, Deserialize, Table)]
+//! struct Numbers {
+//!     #[table(row_id)]
+//!     id: i64,
+//!     square: i64,
+//!     name: String,
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! let db = DB::open("examples/numbers.db")?;
+//! let numbers = db.table::<Numbers>()?;
+//!
+//! let twelve = numbers.get(12)?.unwrap();
+//! assert_eq!((twelve.square, twelve.name.as_str()), (144, "number 12"));
+//!
+//! let ids = numbers
+//!     .get(10..13)?
+//!     .map(|row| row.map(|row| row.id))
+//!     .collect::<Result<Vec<_>>>()?;
+//! assert_eq!(ids, [10, 11, 12]);
+//! # Ok(())
+//! # }
+//! ```
+
 pub mod physical;
 pub mod schema;
@@ -4,7 +4,7 @@ use anyhow::Result;
 use serde::Deserialize;
 use squeak::{
     physical::db::DB,
-    schema::{serialization::row_id, SchemaType, Table, WithRowId},
+    schema::{SchemaType, Table, WithRowId},
 };
 use squeak_macros::Table;
 
@@ -12,8 +12,7 @@ use squeak_macros::Table;
 #[table(name = "crashes")]
 struct Crash {
     #[table(row_id)]
-    #[serde(with = "row_id")]
-    id: u64,
+    id: i64,
     _year: i32,
     _lat: f64,
     _lng: f64,
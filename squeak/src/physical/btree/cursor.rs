@@ -0,0 +1,253 @@
+use std::mem;
+
+use anyhow::{anyhow, Result};
+
+use crate::{physical::buf::ArcBufSlice, schema::record::Record};
+
+use super::{check_depth, find_cell, BTreePage, BTreePageType};
+
+/// A position in a table b-tree that can be moved in either direction.
+///
+/// A new cursor is not positioned on any row; use [`Cursor::seek`],
+/// [`Cursor::first`] or [`Cursor::last`] to position it. Moving past either
+/// end leaves the cursor unpositioned until it is sought again.
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub(super) page: BTreePage,
+    pub(super) index: u16,
+    // The parent pages, and the index of the child that was descended into.
+    pub(super) stack: Vec<(BTreePage, u16)>,
+    pub(super) valid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Cursor {
+    pub(crate) fn new(root: BTreePage) -> Self {
+        Self {
+            page: root,
+            index: 0,
+            stack: Vec::new(),
+            valid: false,
+        }
+    }
+
+    /// Returns the row id and record that the cursor is positioned on, or
+    /// `None` if it is not positioned on a row.
+    pub fn current(&self) -> Result<Option<(i64, Record)>> {
+        let cell = self.current_cell()?;
+        Ok(cell.map(|(row_id, record)| (row_id, record.into())))
+    }
+
+    pub(crate) fn current_cell(&self) -> Result<Option<(i64, ArcBufSlice)>> {
+        if !self.valid {
+            return Ok(None);
+        }
+        self.page.leaf_table_cell(self.index).map(Some)
+    }
+
+    /// Positions the cursor on the first row with an id of at least `row_id`.
+    /// Returns whether there is such a row.
+    pub fn seek(&mut self, row_id: i64) -> Result<bool> {
+        self.reset();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("seek", row_id, root = self.page.page_number).entered();
+        self.seek_from_current(row_id)
+    }
+
+    /// Like [`Cursor::seek`], but only climbs as far up the tree as needed
+    /// from the current position. `row_id` must not be less than the current
+    /// row id.
+    pub(crate) fn seek_forward(&mut self, row_id: i64) -> Result<bool> {
+        loop {
+            let cell_count = self.page.header.cell_count.get();
+            let found = match self.page.page_type() {
+                // The cell at the current index bounds the child we came from.
+                BTreePageType::InteriorTable => find_cell(self.index..cell_count, |index| {
+                    Ok(row_id <= self.page.interior_table_cell(index)?.1)
+                })?
+                .is_some(),
+                BTreePageType::LeafTable => {
+                    cell_count > 0 && row_id <= self.page.leaf_table_cell(cell_count - 1)?.0
+                }
+                ty => return Err(not_a_table_page(ty)),
+            };
+
+            if found || self.stack.is_empty() {
+                break;
+            }
+            self.ascend(self.stack.len() - 1);
+        }
+
+        self.seek_from_current(row_id)
+    }
+
+    fn seek_from_current(&mut self, row_id: i64) -> Result<bool> {
+        loop {
+            let cell_count = self.page.header.cell_count.get();
+            match self.page.page_type() {
+                BTreePageType::InteriorTable => {
+                    // Each cell's key is the largest row id in its left child.
+                    // TODO: binary search
+                    let child_index = find_cell(0..cell_count, |index| {
+                        Ok(row_id <= self.page.interior_table_cell(index)?.1)
+                    })?
+                    .unwrap_or(cell_count);
+                    self.descend(child_index)?;
+                }
+                BTreePageType::LeafTable => {
+                    // TODO: binary search
+                    let index = find_cell(0..cell_count, |index| {
+                        Ok(row_id <= self.page.leaf_table_cell(index)?.0)
+                    })?
+                    .unwrap_or(cell_count);
+                    if index < cell_count {
+                        self.index = index;
+                        self.valid = true;
+                        return Ok(true);
+                    }
+
+                    // All rows on this leaf are too small, so move to the next leaf.
+                    if cell_count == 0 {
+                        self.valid = false;
+                        return Ok(false);
+                    }
+                    self.index = cell_count - 1;
+                    self.valid = true;
+                    return self.next();
+                }
+                ty => return Err(not_a_table_page(ty)),
+            }
+        }
+    }
+
+    /// Positions the cursor on the first row. Returns whether the table has
+    /// any rows.
+    pub fn first(&mut self) -> Result<bool> {
+        self.reset();
+        self.descend_to_end(Direction::Forward)
+    }
+
+    /// Positions the cursor on the last row. Returns whether the table has
+    /// any rows.
+    pub fn last(&mut self) -> Result<bool> {
+        self.reset();
+        self.descend_to_end(Direction::Backward)
+    }
+
+    /// Moves to the following row. Returns `false` if there is none.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<bool> {
+        self.step(Direction::Forward)
+    }
+
+    /// Moves to the preceding row. Returns `false` if there is none.
+    pub fn prev(&mut self) -> Result<bool> {
+        self.step(Direction::Backward)
+    }
+
+    /// Moves one row in the given direction, crossing to the neighbouring
+    /// leaf through the closest ancestor that has a child in that direction.
+    pub(crate) fn step(&mut self, direction: Direction) -> Result<bool> {
+        if !self.valid {
+            return Ok(false);
+        }
+
+        let cell_count = self.page.header.cell_count.get();
+        match direction {
+            Direction::Forward if self.index + 1 < cell_count => {
+                self.index += 1;
+                return Ok(true);
+            }
+            Direction::Backward if self.index > 0 => {
+                self.index -= 1;
+                return Ok(true);
+            }
+            _ => {}
+        }
+
+        // The right-most pointer is the child after the last cell.
+        let has_sibling = |(page, child_index): &(BTreePage, u16)| match direction {
+            Direction::Forward => *child_index < page.header.cell_count.get(),
+            Direction::Backward => *child_index > 0,
+        };
+        let Some(depth) = self.stack.iter().rposition(has_sibling) else {
+            self.valid = false;
+            return Ok(false);
+        };
+
+        self.ascend(depth);
+        let sibling_index = match direction {
+            Direction::Forward => self.index + 1,
+            Direction::Backward => self.index - 1,
+        };
+        self.descend(sibling_index)?;
+        self.descend_to_end(direction)
+    }
+
+    /// Releases every page except the current one, and leaves the cursor
+    /// unpositioned.
+    pub(crate) fn finish(&mut self) {
+        self.stack.clear();
+        self.valid = false;
+    }
+
+    fn reset(&mut self) {
+        if !self.stack.is_empty() {
+            self.ascend(0);
+        }
+        self.index = 0;
+        self.valid = false;
+    }
+
+    /// Returns to the page at the given depth of the stack, with the index set
+    /// to the child that was descended into.
+    fn ascend(&mut self, depth: usize) {
+        (self.page, self.index) = self.stack.drain(depth..).next().unwrap();
+    }
+
+    fn descend(&mut self, child_index: u16) -> Result<()> {
+        let child_page_number = if child_index < self.page.header.cell_count.get() {
+            self.page.interior_table_cell(child_index)?.0
+        } else {
+            self.page.right_most_pointer()
+        };
+        check_depth(self.stack.len() + 1, child_page_number)?;
+        let child_page = self.page.db.btree_page(child_page_number)?;
+        let parent_page = mem::replace(&mut self.page, child_page);
+        self.stack.push((parent_page, child_index));
+        self.index = 0;
+        Ok(())
+    }
+
+    /// Descends to the first row of the current subtree when moving forward,
+    /// or to the last row when moving backward.
+    fn descend_to_end(&mut self, direction: Direction) -> Result<bool> {
+        loop {
+            let cell_count = self.page.header.cell_count.get();
+            match (self.page.page_type(), direction) {
+                (BTreePageType::InteriorTable, Direction::Forward) => self.descend(0)?,
+                (BTreePageType::InteriorTable, Direction::Backward) => self.descend(cell_count)?,
+                (BTreePageType::LeafTable, _) => {
+                    self.index = match direction {
+                        Direction::Forward => 0,
+                        Direction::Backward => cell_count.saturating_sub(1),
+                    };
+                    self.valid = cell_count > 0;
+                    return Ok(self.valid);
+                }
+                (ty, _) => return Err(not_a_table_page(ty)),
+            }
+        }
+    }
+}
+
+/// The error for a cursor opened on an index or `WITHOUT ROWID` table, whose
+/// b-tree is not keyed by row id.
+fn not_a_table_page(ty: BTreePageType) -> anyhow::Error {
+    anyhow!("expected a table b-tree page, found {ty:?}")
+}
@@ -1,17 +1,30 @@
 use std::{cmp::Ordering, mem, ops::Range};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use crate::physical::buf::ArcBufSlice;
 
-use super::{BTreePage, BTreePageType};
+use super::{
+    check_depth,
+    cursor::{Cursor, Direction},
+    find_cell, BTreePage, BTreePageType,
+};
 
+/// Iterates over the cells of a table b-tree in row id order.
+///
+/// Only the pages on the path from the root to the current leaf are held, and
+/// each is released once the iterator ascends past it. The ancestors of the
+/// current leaf are released as soon as the end of the range is reached.
 pub struct BTreeTableEntries {
-    page: BTreePage,
-    index: u16,
-    stack: Vec<(BTreePage, u16)>,
+    cursor: Cursor,
+    // Whether the cursor has been positioned yet
+    started: bool,
+    // Whether the cell under the cursor is still to be yielded
+    pending: bool,
     // Exclusive upper bound
-    max_row_id: Option<u64>,
+    max_row_id: Option<i64>,
+    // Inclusive lower bound on the remaining row ids, if known
+    min_row_id: Option<i64>,
 }
 
 pub struct BTreeIndexEntries<C> {
@@ -25,104 +38,100 @@ pub struct BTreeIndexEntries<C> {
 impl BTreeTableEntries {
     pub(super) fn new(page: BTreePage) -> Self {
         Self {
-            page,
-            index: 0,
-            stack: Vec::new(),
+            cursor: Cursor::new(page),
+            started: false,
+            pending: false,
             max_row_id: None,
+            min_row_id: None,
         }
     }
 
-    pub(super) fn with_range(page: BTreePage, range: Range<Option<u64>>) -> Result<Self> {
+    pub(super) fn with_range(page: BTreePage, range: Range<Option<i64>>) -> Result<Self> {
         let mut entries = Self::new(page);
 
         if let Some(start) = range.start {
-            entries.seek(start)?;
+            entries.cursor.seek(start)?;
+            entries.started = true;
+            entries.pending = true;
         }
+        entries.min_row_id = range.start;
         entries.max_row_id = range.end;
 
         Ok(entries)
     }
 
-    fn seek(&mut self, row_id: u64) -> Result<()> {
-        loop {
-            match self.page.page_type() {
-                BTreePageType::InteriorTable => {
-                    // TODO: binary search
-                    let mut child_page_index = 0;
-                    for index in 0..self.page.header.cell_count.get() {
-                        let (_page_number, current_id) = self.page.interior_table_cell(index);
-                        if current_id > row_id {
-                            break;
-                        } else {
-                            child_page_index = index + 1;
-                        }
-                    }
+    /// Like [`Cursor::seek`], but starts from the current position and only
+    /// climbs as far up the tree as needed. `row_id` must not be less than the
+    /// last row id that was sought.
+    pub(crate) fn seek_forward(&mut self, row_id: i64) -> Result<()> {
+        self.cursor.seek_forward(row_id)?;
+        self.started = true;
+        self.pending = true;
+        Ok(())
+    }
 
-                    let (child_page_number, _id) = self.page.interior_table_cell(child_page_index);
-                    let child_page = self.page.db.btree_page(child_page_number)?;
-                    let parent_page = mem::replace(&mut self.page, child_page);
-                    self.stack.push((parent_page, child_page_index + 1));
-                }
-                BTreePageType::LeafTable => {
-                    // TODO: binary search
-                    let mut leaf_index = 0;
-                    for index in 0..self.page.header.cell_count.get() {
-                        let (current_id, _data) = self.page.leaf_table_cell(index);
-                        if current_id > row_id {
-                            break;
-                        } else {
-                            leaf_index = index;
-                        }
-                    }
-                    self.index = leaf_index;
-                    return Ok(());
-                }
-                ty => todo!("{ty:?}"),
-            }
+    fn step(&mut self) -> Result<Option<(i64, ArcBufSlice)>> {
+        if !self.started {
+            self.cursor.first()?;
+            self.started = true;
+        } else if !self.pending {
+            self.cursor.step(Direction::Forward)?;
+        }
+        self.pending = false;
+
+        let Some((row_id, record)) = self.cursor.current_cell()? else {
+            self.cursor.finish();
+            return Ok(None);
+        };
+        if self
+            .max_row_id
+            .is_some_and(|max_row_id| row_id >= max_row_id)
+        {
+            self.cursor.finish();
+            return Ok(None);
         }
+
+        self.min_row_id = row_id.checked_add(1);
+        Ok(Some((row_id, record)))
     }
 }
 
 impl Iterator for BTreeTableEntries {
-    type Item = Result<(u64, ArcBufSlice)>;
+    type Item = Result<(i64, ArcBufSlice)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if self.index < self.page.header.cell_count.get() {
-                match self.page.page_type() {
-                    BTreePageType::InteriorTable => {
-                        let (page_number, _row_id) = self.page.interior_table_cell(self.index);
-                        self.index += 1;
-
-                        let mut page = match self.page.db.btree_page(page_number) {
-                            Ok(page) => page,
-                            Err(err) => return Some(Err(err)),
-                        };
-
-                        mem::swap(&mut self.page, &mut page);
-                        self.stack.push((page, self.index));
-                        self.index = 0;
-                    }
-                    BTreePageType::LeafTable => {
-                        let (row_id, record) = self.page.leaf_table_cell(self.index);
-                        self.index += 1;
+        self.step().transpose()
+    }
 
-                        if let Some(max_row_id) = self.max_row_id {
-                            if row_id >= max_row_id {
-                                return None;
-                            }
-                        }
+    /// The lower bound counts the rows left on the current leaf, and the
+    /// upper bound comes from the number of row ids left in the range.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.started && !self.cursor.valid {
+            return (0, Some(0));
+        }
 
-                        return Some(Ok((row_id, record)));
-                    }
-                    _ => todo!("{:?}", self.page.page_type()),
-                }
-            } else if let Some(popped) = self.stack.pop() {
-                (self.page, self.index) = popped;
-            } else {
-                return None;
+        let in_range = |row_id: i64| self.max_row_id.is_none_or(|max| row_id < max);
+        let lower = if self.started {
+            let page = &self.cursor.page;
+            let start = self.cursor.index + u16::from(!self.pending);
+            (start..page.header.cell_count.get())
+                .take_while(|&index| {
+                    page.leaf_table_cell(index)
+                        .is_ok_and(|(row_id, _)| in_range(row_id))
+                })
+                .count()
+        } else {
+            0
+        };
+
+        let upper = match (self.min_row_id, self.max_row_id) {
+            (Some(min), Some(max)) => {
+                let span = (max as i128 - min as i128).max(0);
+                Some(usize::try_from(span).unwrap_or(usize::MAX))
             }
-        }
+            _ => None,
+        };
+        (lower, upper.map(|upper| upper.max(lower)))
     }
 }
 
@@ -140,44 +149,66 @@ impl<C: PartialOrd<ArcBufSlice>> BTreeIndexEntries<C> {
         Ok(entries)
     }
 
+    /// Positions the iterator at the first entry that is not before the range.
     fn seek_start(&mut self) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("seek", root = self.page.page_number).entered();
+
         loop {
+            let cell_count = self.page.header.cell_count.get();
             match self.page.page_type() {
                 BTreePageType::InteriorIndex => {
+                    // Each cell's key is greater than every key in its left child.
                     // TODO: binary search
-                    let mut child_page_index = 0;
-                    for index in 0..self.page.header.cell_count.get() {
-                        let (_page_number, current_key) = self.page.interior_index_cell(index);
-                        if self.comparator < current_key {
-                            child_page_index = index;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    let (child_page_number, _key) = self.page.interior_index_cell(child_page_index);
-                    let child_page = self.page.db.btree_page(child_page_number)?;
-                    let parent_page = mem::replace(&mut self.page, child_page);
-                    self.stack.push((parent_page, child_page_index + 1));
+                    let child_page_index = find_cell(0..cell_count, |index| {
+                        let (_page_number, key) = self.page.interior_index_cell(index)?;
+                        Ok(self.comparator.partial_cmp(&key) != Some(Ordering::Greater))
+                    })?
+                    .unwrap_or(cell_count);
+                    self.descend(child_page_index)?;
                 }
                 BTreePageType::LeafIndex => {
                     // TODO: binary search
-                    let mut leaf_index = 0;
-                    for index in 0..self.page.header.cell_count.get() {
-                        let current_key = self.page.leaf_index_cell(index);
-                        if self.comparator < current_key {
-                            leaf_index = index;
-                        } else {
-                            break;
-                        }
-                    }
-                    self.index = leaf_index;
+                    self.index = find_cell(0..cell_count, |index| {
+                        let key = self.page.leaf_index_cell(index)?;
+                        Ok(self.comparator.partial_cmp(&key) != Some(Ordering::Greater))
+                    })?
+                    .unwrap_or(cell_count);
                     return Ok(());
                 }
-                ty => todo!("{ty:?}"),
+                ty => return Err(anyhow!("expected an index b-tree page, found {ty:?}")),
             }
         }
     }
+
+    /// Moves to the given child of the current interior page. The parent is
+    /// resumed at the same index, which is the cell that follows the child.
+    fn descend(&mut self, child_page_index: u16) -> Result<()> {
+        let child_page_number = if child_page_index < self.page.header.cell_count.get() {
+            self.page.interior_index_cell(child_page_index)?.0
+        } else {
+            self.page.right_most_pointer()
+        };
+        check_depth(self.stack.len() + 1, child_page_number)?;
+        let child_page = self.page.db.btree_page(child_page_number)?;
+        let parent_page = mem::replace(&mut self.page, child_page);
+        self.stack.push((parent_page, child_page_index));
+        self.index = 0;
+        Ok(())
+    }
+
+    fn descend_left_most(&mut self, child_page_index: u16) -> Result<()> {
+        self.descend(child_page_index)?;
+        while self.page.page_type() == BTreePageType::InteriorIndex {
+            self.descend(0)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.stack.clear();
+        self.index = self.page.header.cell_count.get();
+    }
 }
 
 impl<C: PartialOrd<ArcBufSlice>> Iterator for BTreeIndexEntries<C> {
@@ -185,37 +216,48 @@ impl<C: PartialOrd<ArcBufSlice>> Iterator for BTreeIndexEntries<C> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            if self.index < self.page.header.cell_count.get() {
-                match self.page.page_type() {
-                    BTreePageType::InteriorIndex => {
-                        let (page_number, _payload) = self.page.interior_index_cell(self.index);
-                        self.index += 1;
-
-                        let mut page = match self.page.db.btree_page(page_number) {
-                            Ok(page) => page,
-                            Err(err) => return Some(Err(err)),
-                        };
-
-                        mem::swap(&mut self.page, &mut page);
-                        self.stack.push((page, self.index));
-                        self.index = 0;
+            let cell_count = self.page.header.cell_count.get();
+            let record = match self.page.page_type() {
+                BTreePageType::LeafIndex if self.index < cell_count => {
+                    let record = self.page.leaf_index_cell(self.index);
+                    self.index += 1;
+                    match record {
+                        Ok(record) => record,
+                        Err(err) => return Some(Err(err)),
                     }
-                    BTreePageType::LeafIndex => {
-                        let record = self.page.leaf_index_cell(self.index);
-                        self.index += 1;
-
-                        match self.comparator.partial_cmp(&record) {
-                            Some(Ordering::Less) => return None,
-                            Some(Ordering::Equal) => return Some(Ok(record)),
-                            _ => continue,
-                        }
+                }
+                BTreePageType::InteriorIndex if self.index < cell_count => {
+                    // Interior cells hold entries too, between their left child
+                    // and the following child.
+                    let (_page_number, record) = match self.page.interior_index_cell(self.index) {
+                        Ok(cell) => cell,
+                        Err(err) => return Some(Err(err)),
+                    };
+                    if self.comparator.partial_cmp(&record) == Some(Ordering::Less) {
+                        self.finish();
+                        return None;
+                    }
+
+                    self.index += 1;
+                    if let Err(err) = self.descend_left_most(self.index) {
+                        return Some(Err(err));
                     }
-                    _ => todo!("{:?}", self.page.page_type()),
+                    record
+                }
+                BTreePageType::LeafIndex | BTreePageType::InteriorIndex => {
+                    (self.page, self.index) = self.stack.pop()?;
+                    continue;
+                }
+                ty => return Some(Err(anyhow!("expected an index b-tree page, found {ty:?}"))),
+            };
+
+            match self.comparator.partial_cmp(&record) {
+                Some(Ordering::Less) => {
+                    self.finish();
+                    return None;
                 }
-            } else if let Some(popped) = self.stack.pop() {
-                (self.page, self.index) = popped;
-            } else {
-                return None;
+                Some(Ordering::Equal) => return Some(Ok(record)),
+                _ => continue,
             }
         }
     }
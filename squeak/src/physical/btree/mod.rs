@@ -1,15 +1,19 @@
-use std::ops::Range;
+use std::{fmt, ops::Range};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use zerocopy::{
     big_endian::{U16, U32},
     FromBytes,
 };
 
-use crate::physical::{buf::ArcBufSlice, db::DB, header::HEADER_SIZE, varint};
+use crate::physical::{buf::ArcBufSlice, db::DB, header::HEADER_SIZE};
 
-use self::iter::{BTreeIndexEntries, BTreeTableEntries};
+use self::{
+    cursor::Direction,
+    iter::{BTreeIndexEntries, BTreeTableEntries},
+};
 
+pub mod cursor;
 pub mod iter;
 
 #[derive(Debug, Clone)]
@@ -50,35 +54,105 @@ struct BTreePageHeader {
     /// The number of fragmented free bytes within the cell content area.
     fragmented_free_bytes: u8,
     /// The right-most pointer. Only valid for interior (non-leaf) pages.
-    right_most_pointer: U16,
+    right_most_pointer: U32,
+}
+
+/// SQLite never builds b-trees deeper than this, so a deeper path means that
+/// the interior pages form a cycle or the file is otherwise corrupt.
+pub(crate) const MAX_DEPTH: usize = 20;
+
+pub(crate) fn check_depth(depth: usize, page_number: u32) -> Result<()> {
+    if depth >= MAX_DEPTH {
+        return Err(anyhow!(
+            "corrupt b-tree: page {page_number} is more than {MAX_DEPTH} levels deep, so the tree may contain a cycle"
+        ));
+    }
+    Ok(())
+}
+
+/// The offset of the b-tree page header, which follows the database header
+/// on page 1. Cell pointers are still relative to the start of the page.
+fn header_start(page_number: u32) -> usize {
+    if page_number == 1 {
+        HEADER_SIZE
+    } else {
+        0
+    }
 }
 
 impl BTreePage {
-    pub(crate) fn new(db: DB, page_number: u32, data: ArcBufSlice) -> BTreePage {
-        let start = if page_number == 1 { HEADER_SIZE } else { 0 };
-        let header = BTreePageHeader::read_from_prefix(&data[start..]).unwrap();
-        header.validate();
+    /// `data` must already be truncated to the usable size of the page, so
+    /// that reserved bytes at the end are never read as cell content.
+    pub(crate) fn new(db: DB, page_number: u32, data: ArcBufSlice) -> Result<BTreePage> {
+        let header = BTreePageHeader::read_from_prefix(&data[header_start(page_number)..]).unwrap();
+        if !header.is_valid() {
+            return Err(anyhow!(
+                "page {page_number} is not a b-tree page (flags {:#04x})",
+                header.flags
+            ));
+        }
 
-        BTreePage {
+        let page = BTreePage {
             db,
             page_number,
             header,
             data,
+        };
+        page.check_cell_pointers()?;
+        Ok(page)
+    }
+
+    /// Checks that every cell pointer lands in the cell content area, so that
+    /// a corrupt pointer is reported here rather than causing a panic when the
+    /// cell is read.
+    fn check_cell_pointers(&self) -> Result<()> {
+        let page_number = self.page_number;
+        let cell_count = self.header.cell_count.get();
+        let pointers_end =
+            header_start(page_number) + self.header.size() as usize + cell_count as usize * 2;
+        let content_start = match self.header.cell_content_start.get() {
+            0 => 65536,
+            start => start as usize,
+        };
+        if pointers_end > content_start || pointers_end > self.data.len() {
+            return Err(anyhow!(
+                "page {page_number} has {cell_count} cell pointers, which overlap the cell content area at {content_start}"
+            ));
+        }
+
+        let content = content_start..self.data.len();
+        for cell_index in 0..cell_count {
+            let ptr = self.cell_pointer(cell_index) as usize;
+            if !content.contains(&ptr) {
+                return Err(anyhow!(
+                    "page {page_number} has cell {cell_index} at offset {ptr}, outside the cell content area {content:?}"
+                ));
+            }
         }
+        Ok(())
+    }
+
+    pub fn page_number(&self) -> u32 {
+        self.page_number
+    }
+
+    /// The bytes of the whole page, including the database header on page 1.
+    pub fn raw(&self) -> &[u8] {
+        &self.data
     }
 
     pub fn page_type(&self) -> BTreePageType {
         self.header.page_type()
     }
 
+    pub fn cell_count(&self) -> u16 {
+        self.header.cell_count.get()
+    }
+
     fn cell_pointer(&self, cell_index: u16) -> u16 {
         assert!(cell_index < self.header.cell_count.get());
-        let start = if self.page_number == 1 {
-            HEADER_SIZE
-        } else {
-            0
-        } + self.header.size() as usize
-            + cell_index as usize * 2;
+        let start =
+            header_start(self.page_number) + self.header.size() as usize + cell_index as usize * 2;
         U16::read_from_prefix(&self.data[start..]).unwrap().get()
     }
 
@@ -89,54 +163,238 @@ impl BTreePage {
         data
     }
 
-    pub(crate) fn leaf_table_cell(&self, cell_index: u16) -> (u64, ArcBufSlice) {
+    fn right_most_pointer(&self) -> u32 {
+        assert!(!self.page_type().is_leaf());
+        self.header.right_most_pointer.get()
+    }
+
+    pub(crate) fn leaf_table_cell(&self, cell_index: u16) -> Result<(i64, ArcBufSlice)> {
         assert_eq!(self.page_type(), BTreePageType::LeafTable);
 
         // TODO: Handle when a cell overflows onto a separate page.
         let mut cell = self.cell(cell_index);
-        let payload_size = cell.consume_varint();
-        let row_id = cell.consume_varint();
-        cell.truncate(payload_size as usize);
+        let payload_size = self.consume_cell_varint(cell_index, &mut cell)?;
+        let row_id = self.consume_cell_varint(cell_index, &mut cell)? as i64;
+        self.truncate_payload(cell_index, &mut cell, payload_size)?;
 
-        (row_id, cell)
+        Ok((row_id, cell))
     }
 
-    pub(crate) fn interior_table_cell(&self, cell_index: u16) -> (u32, u64) {
+    pub(crate) fn interior_table_cell(&self, cell_index: u16) -> Result<(u32, i64)> {
         assert_eq!(self.page_type(), BTreePageType::InteriorTable);
 
-        let cell = self.cell(cell_index);
-        let left_child_page_number = U32::read_from_prefix(&cell).unwrap().get();
-        let (row_id, _) = varint::read(&cell[4..]);
+        let mut cell = self.cell(cell_index);
+        let left_child_page_number = self.consume_child_pointer(cell_index, &mut cell)?;
+        let row_id = self.consume_cell_varint(cell_index, &mut cell)?;
 
-        (left_child_page_number, row_id)
+        Ok((left_child_page_number, row_id as i64))
     }
 
-    pub(crate) fn leaf_index_cell(&self, cell_index: u16) -> ArcBufSlice {
+    pub(crate) fn leaf_index_cell(&self, cell_index: u16) -> Result<ArcBufSlice> {
         assert_eq!(self.page_type(), BTreePageType::LeafIndex);
 
         // TODO: Handle when a cell overflows onto a separate page.
         let mut cell = self.cell(cell_index);
-        let payload_size = cell.consume_varint();
-        cell.truncate(payload_size as usize);
+        let payload_size = self.consume_cell_varint(cell_index, &mut cell)?;
+        self.truncate_payload(cell_index, &mut cell, payload_size)?;
 
-        cell
+        Ok(cell)
     }
 
-    pub(crate) fn interior_index_cell(&self, cell_index: u16) -> (u32, ArcBufSlice) {
+    pub(crate) fn interior_index_cell(&self, cell_index: u16) -> Result<(u32, ArcBufSlice)> {
         assert_eq!(self.page_type(), BTreePageType::InteriorIndex);
 
         // TODO: Handle when a cell overflows onto a separate page.
         let mut cell = self.cell(cell_index);
-        let left_child_page_number = U32::read_from_prefix(&cell).unwrap().get();
-        let payload_size = cell.consume_varint();
+        let left_child_page_number = self.consume_child_pointer(cell_index, &mut cell)?;
+        let payload_size = self.consume_cell_varint(cell_index, &mut cell)?;
+        self.truncate_payload(cell_index, &mut cell, payload_size)?;
+
+        Ok((left_child_page_number, cell))
+    }
+
+    fn consume_cell_varint(&self, cell_index: u16, cell: &mut ArcBufSlice) -> Result<u64> {
+        cell.consume_varint()
+            .map_err(|err| self.cell_error(cell_index, err))
+    }
+
+    fn consume_child_pointer(&self, cell_index: u16, cell: &mut ArcBufSlice) -> Result<u32> {
+        if cell.len() < 4 {
+            return Err(self.cell_error(
+                cell_index,
+                "child page number runs past the end of the page",
+            ));
+        }
+        Ok(cell.consume::<U32>().get())
+    }
+
+    fn truncate_payload(
+        &self,
+        cell_index: u16,
+        cell: &mut ArcBufSlice,
+        payload_size: u64,
+    ) -> Result<()> {
+        if payload_size > cell.len() as u64 {
+            return Err(self.cell_error(
+                cell_index,
+                format!("{payload_size}-byte payload runs past the end of the page"),
+            ));
+        }
         cell.truncate(payload_size as usize);
+        Ok(())
+    }
+
+    fn cell_error(&self, cell_index: u16, err: impl fmt::Display) -> anyhow::Error {
+        anyhow!(
+            "malformed cell {cell_index} on page {}: {err}",
+            self.page_number
+        )
+    }
+
+    /// Counts the entries in the b-tree rooted at this page, without decoding
+    /// any records.
+    pub fn count_entries(&self) -> Result<u64> {
+        self.count_entries_at(0)
+    }
+
+    fn count_entries_at(&self, depth: usize) -> Result<u64> {
+        let mut count = match self.page_type() {
+            BTreePageType::LeafTable | BTreePageType::LeafIndex => {
+                return Ok(self.header.cell_count.get() as u64)
+            }
+            BTreePageType::InteriorTable => 0,
+            // Unlike interior table cells, interior index cells hold entries.
+            BTreePageType::InteriorIndex => self.header.cell_count.get() as u64,
+        };
+        for page_number in self.child_page_numbers()? {
+            check_depth(depth + 1, page_number)?;
+            count += self
+                .db
+                .btree_page(page_number)?
+                .count_entries_at(depth + 1)?;
+        }
+        Ok(count)
+    }
+
+    /// The children of an interior page, from left to right. Leaf pages have
+    /// none.
+    pub(crate) fn child_page_numbers(&self) -> Result<Vec<u32>> {
+        let cell_count = self.header.cell_count.get();
+        let mut children = match self.page_type() {
+            BTreePageType::LeafTable | BTreePageType::LeafIndex => return Ok(Vec::new()),
+            BTreePageType::InteriorTable => (0..cell_count)
+                .map(|index| Ok(self.interior_table_cell(index)?.0))
+                .collect::<Result<Vec<_>>>()?,
+            BTreePageType::InteriorIndex => (0..cell_count)
+                .map(|index| Ok(self.interior_index_cell(index)?.0))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        children.push(self.right_most_pointer());
+        Ok(children)
+    }
+
+    /// Follows the left-most children down to a leaf when moving forward, or
+    /// the right-most children when moving backward. The smallest and largest
+    /// entries of a b-tree are on these leaves.
+    pub(crate) fn end_leaf(&self, direction: Direction) -> Result<BTreePage> {
+        let mut page = self.clone();
+        let mut depth = 0;
+        while !page.page_type().is_leaf() {
+            let children = page.child_page_numbers()?;
+            let child = match direction {
+                Direction::Forward => children[0],
+                Direction::Backward => children[children.len() - 1],
+            };
+            depth += 1;
+            check_depth(depth, child)?;
+            page = self.db.btree_page(child)?;
+        }
+        Ok(page)
+    }
+
+    /// The pages of the overflow chain of a cell, in order. Empty if the
+    /// payload of the cell fits on this page.
+    pub fn cell_overflow_pages(&self, cell_index: u16) -> Result<Vec<u32>> {
+        let page_number = self.page_number;
+        if cell_index >= self.cell_count() {
+            return Err(anyhow!(
+                "page {page_number} has no cell {cell_index}, as it only has {}",
+                self.cell_count()
+            ));
+        }
+        let Some((mut overflow, overflow_size)) = self.first_overflow_page(cell_index)? else {
+            return Ok(Vec::new());
+        };
+
+        // Each overflow page starts with the number of the next one.
+        let expected = overflow_size.div_ceil(self.data.len() as u64 - 4);
+        let mut pages = Vec::new();
+        while overflow != 0 {
+            if pages.len() as u64 == expected {
+                return Err(anyhow!(
+                    "cell {cell_index} on page {page_number} has more than the {expected} overflow pages its payload needs"
+                ));
+            }
+            pages.push(overflow);
+            overflow = self.db.page(overflow)?.consume::<U32>().get();
+        }
+        if (pages.len() as u64) < expected {
+            return Err(anyhow!(
+                "cell {cell_index} on page {page_number} has {} overflow pages, but its payload needs {expected}",
+                pages.len()
+            ));
+        }
+        Ok(pages)
+    }
+
+    /// The first overflow page of a cell, and how many bytes of its payload
+    /// are stored on overflow pages. `None` if the payload fits on this page.
+    fn first_overflow_page(&self, cell_index: u16) -> Result<Option<(u32, u64)>> {
+        let page_type = self.page_type();
+        // The data is truncated to the usable size of the page.
+        let usable_size = self.data.len() as u64;
+        let max_local = match page_type {
+            BTreePageType::InteriorTable => return Ok(None),
+            BTreePageType::LeafTable => usable_size - 35,
+            BTreePageType::InteriorIndex | BTreePageType::LeafIndex => {
+                (usable_size - 12) * 64 / 255 - 23
+            }
+        };
+        let min_local = (usable_size - 12) * 32 / 255 - 23;
+
+        let mut cell = self.cell(cell_index);
+        if page_type == BTreePageType::InteriorIndex {
+            cell.consume_bytes(4);
+        }
+        let payload_size = cell.consume_varint()?;
+        if page_type == BTreePageType::LeafTable {
+            cell.consume_varint()?;
+        }
+        if payload_size <= max_local {
+            return Ok(None);
+        }
 
-        (left_child_page_number, cell)
+        let local_size = min_local + (payload_size - min_local) % (usable_size - 4);
+        let local_size = if local_size <= max_local {
+            local_size
+        } else {
+            min_local
+        };
+        let page_number = cell
+            .get(local_size as usize..)
+            .and_then(U32::read_from_prefix)
+            .ok_or_else(|| {
+                anyhow!(
+                    "cell {cell_index} on page {} runs past the end of the page",
+                    self.page_number
+                )
+            })?;
+        Ok(Some((page_number.get(), payload_size - local_size)))
     }
 
     pub(crate) fn into_table_entries_range(
         self,
-        range: Range<Option<u64>>,
+        range: Range<Option<i64>>,
     ) -> Result<BTreeTableEntries> {
         BTreeTableEntries::with_range(self, range)
     }
@@ -149,6 +407,20 @@ impl BTreePage {
     }
 }
 
+/// Finds the first of `cells` for which `predicate` holds, stopping at the
+/// first cell that cannot be read.
+fn find_cell(
+    cells: Range<u16>,
+    mut predicate: impl FnMut(u16) -> Result<bool>,
+) -> Result<Option<u16>> {
+    for index in cells {
+        if predicate(index)? {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
 impl BTreePageType {
     fn is_leaf(self) -> bool {
         match self {
@@ -159,8 +431,8 @@ impl BTreePageType {
 }
 
 impl BTreePageHeader {
-    fn validate(&self) {
-        assert!([0x02, 0x05, 0x0a, 0x0d].contains(&self.flags));
+    fn is_valid(&self) -> bool {
+        [0x02, 0x05, 0x0a, 0x0d].contains(&self.flags)
     }
 
     fn page_type(&self) -> BTreePageType {
@@ -1,7 +1,8 @@
 use std::{fmt, mem, ops::Deref, sync::Arc};
 
-use crate::physical::varint;
+use anyhow::Result;
 
+use crate::physical::varint;
 
 pub type ArcBuf = Arc<[u8]>;
 
@@ -13,6 +14,12 @@ pub struct ArcBufSlice {
 }
 
 impl ArcBufSlice {
+    /// Creates a slice of `buf` from `start` up to `end`.
+    pub fn new(buf: ArcBuf, start: usize, end: usize) -> Self {
+        assert!(start <= end && end <= buf.len());
+        Self { buf, start, end }
+    }
+
     pub fn consume_bytes(&mut self, count: usize) -> &[u8] {
         let consume_to = self.start + count;
         debug_assert!(consume_to <= self.end);
@@ -22,10 +29,10 @@ impl ArcBufSlice {
         bytes
     }
 
-    pub fn consume_varint(&mut self) -> u64 {
-        let (result, len) = varint::read(self);
+    pub fn consume_varint(&mut self) -> Result<u64> {
+        let (result, len) = varint::read(self)?;
         self.consume_bytes(len);
-        result
+        Ok(result)
     }
 
     pub fn consume<T: zerocopy::FromBytes>(&mut self) -> T {
@@ -0,0 +1,43 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Stores pages that have been read from the database file. Implementations
+/// may drop pages at any time, in which case they will be read again.
+///
+/// Lookups may run on several threads at once, while insertions and clearing
+/// are exclusive.
+pub trait PageCache: Send + Sync {
+    fn get(&self, page_number: u32) -> Option<Arc<[u8]>>;
+
+    fn insert(&mut self, page_number: u32, page: Arc<[u8]>);
+
+    fn clear(&mut self);
+}
+
+/// Keeps every page that has been read. This is the default cache.
+impl PageCache for BTreeMap<u32, Arc<[u8]>> {
+    fn get(&self, page_number: u32) -> Option<Arc<[u8]>> {
+        BTreeMap::get(self, &page_number).cloned()
+    }
+
+    fn insert(&mut self, page_number: u32, page: Arc<[u8]>) {
+        BTreeMap::insert(self, page_number, page);
+    }
+
+    fn clear(&mut self) {
+        BTreeMap::clear(self);
+    }
+}
+
+/// Never keeps any pages, so every access reads from the file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCache;
+
+impl PageCache for NoCache {
+    fn get(&self, _page_number: u32) -> Option<Arc<[u8]>> {
+        None
+    }
+
+    fn insert(&mut self, _page_number: u32, _page: Arc<[u8]>) {}
+
+    fn clear(&mut self) {}
+}
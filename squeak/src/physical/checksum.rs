@@ -0,0 +1,27 @@
+//! Page checksums as written by SQLite's checksum VFS extension (cksumvfs),
+//! which stores them in the last 8 bytes of each page.
+
+/// The number of reserved bytes per page that hold the checksum.
+pub(crate) const CHECKSUM_SIZE: usize = 8;
+
+/// Computes the checksum of everything on the page before the checksum itself.
+pub(crate) fn compute(page: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let data = &page[..page.len() - CHECKSUM_SIZE];
+
+    let (mut s1, mut s2) = (0u32, 0u32);
+    for words in data.chunks_exact(8) {
+        let word = |i: usize| u32::from_le_bytes(words[i..i + 4].try_into().unwrap());
+        s1 = s1.wrapping_add(word(0)).wrapping_add(s2);
+        s2 = s2.wrapping_add(word(4)).wrapping_add(s1);
+    }
+
+    let mut checksum = [0; CHECKSUM_SIZE];
+    checksum[..4].copy_from_slice(&s1.to_le_bytes());
+    checksum[4..].copy_from_slice(&s2.to_le_bytes());
+    checksum
+}
+
+/// Checks the checksum stored at the end of the page.
+pub(crate) fn verify(page: &[u8]) -> bool {
+    page[page.len() - CHECKSUM_SIZE..] == compute(page)
+}
@@ -1,86 +1,543 @@
 use std::{
-    collections::{btree_map::Entry, BTreeMap},
+    collections::BTreeMap,
     fmt,
     fs::File,
     io::{Read, Seek, SeekFrom},
-    sync::{Arc, Mutex},
+    sync::{Arc, RwLock},
 };
 
 use anyhow::{anyhow, Result};
 
-use crate::physical::{btree::BTreePage, buf::ArcBuf, header::Header};
+use zerocopy::big_endian::U32;
 
+use crate::{
+    physical::{
+        btree::{check_depth, BTreePage, BTreePageType},
+        buf::{ArcBuf, ArcBufSlice},
+        cache::PageCache,
+        checksum,
+        header::{Header, HEADER_SIZE},
+    },
+    schema::Schema,
+};
+
+/// A handle to an open database. Clones are cheap and share the file and
+/// page cache, so a `DB` can be sent to other threads to read concurrently.
+/// Pages are handed out as reference-counted buffers, so a page that is in
+/// use stays valid even if the cache drops it.
 #[derive(Clone)]
 pub struct DB {
-    pub(crate) state: Arc<Mutex<DBState>>,
+    pub(crate) state: Arc<RwLock<DBState>>,
+}
+
+/// Details of the database file, as recorded in its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbInfo {
+    pub page_size: u32,
+    pub page_count: u32,
+    /// `None` if the header holds an unrecognised encoding.
+    pub text_encoding: Option<TextEncoding>,
+    pub schema_format: u32,
+    pub write_version: FileFormat,
+    pub read_version: FileFormat,
+    pub freelist_count: u32,
+    /// The largest root page number in an auto-vacuumed database, or 0 if
+    /// the database is not auto-vacuumed.
+    pub largest_root_page: u32,
+    pub user_version: u32,
+    pub application_id: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+/// The file format versions in the header, which say whether the database
+/// uses a rollback journal or a write-ahead log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Legacy,
+    Wal,
 }
 
+impl FileFormat {
+    fn from_version(version: u8) -> Self {
+        if version == 2 {
+            Self::Wal
+        } else {
+            Self::Legacy
+        }
+    }
+}
+
+/// What a page of the database file is used for. See [`DB::page_map`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    /// Page 1, which holds the database header and the root of the schema
+    /// table.
+    Header,
+    TableLeaf,
+    TableInterior,
+    IndexLeaf,
+    IndexInterior,
+    Overflow,
+    FreelistTrunk,
+    FreelistLeaf,
+    Ptrmap,
+}
+
+/// Where the pages of a database are read from.
 #[derive(Debug)]
+enum Storage {
+    File(File),
+    // Pages are sliced straight out of the image, without copying.
+    Bytes(ArcBuf),
+}
+
 pub(crate) struct DBState {
-    file: File,
-    pages: BTreeMap<u32, ArcBuf>,
+    file: Storage,
+    // Where page 1 starts in the file, for databases embedded in other files
+    base_offset: u64,
+    pages: Box<dyn PageCache>,
     header: Header,
+    on_page_access: Option<Box<dyn Fn(u32, bool) + Send + Sync>>,
+    verify_checksums: bool,
 }
 
 impl DB {
     pub fn open(path: &str) -> Result<Self> {
-        let file = File::open(path)?;
+        Self::open_with_cache(path, BTreeMap::new())
+    }
 
-        let mut state = DBState {
-            file,
-            pages: BTreeMap::new(),
-            header: Header::default(),
-        };
+    /// Like [`DB::open`], but only logs a warning for header fields that are
+    /// unusual but do not prevent reading the file.
+    pub fn open_lenient(path: &str) -> Result<Self> {
+        Self::open_impl(
+            Storage::File(File::open(path)?),
+            0,
+            Box::new(BTreeMap::new()),
+            true,
+        )
+    }
+
+    /// Opens the database using the given cache for pages read from the file.
+    pub fn open_with_cache(path: &str, cache: impl PageCache + 'static) -> Result<Self> {
+        Self::open_impl(Storage::File(File::open(path)?), 0, Box::new(cache), false)
+    }
+
+    /// Opens the database in a file that is already open, such as an unnamed
+    /// temporary file. The file does not need to be at any particular
+    /// position, as every read seeks first.
+    pub fn from_file(file: File) -> Result<Self> {
+        Self::open_impl(Storage::File(file), 0, Box::new(BTreeMap::new()), false)
+    }
+
+    /// Opens a database held in memory. The database starts `base_offset`
+    /// bytes into the buffer, so that databases embedded in other files (such
+    /// as one appended to an executable) can be read in place.
+    ///
+    /// Records borrow from `bytes` rather than copying pages out of it, so
+    /// passing an `Arc<[u8]>` that is already shared avoids any copies.
+    pub fn open_bytes(bytes: impl Into<ArcBuf>, base_offset: u64) -> Result<Self> {
+        Self::open_impl(
+            Storage::Bytes(bytes.into()),
+            base_offset,
+            Box::new(BTreeMap::new()),
+            false,
+        )
+    }
+
+    /// Reads the whole of a stream, such as a pipe, into memory and opens
+    /// the database in it, as with [`DB::open_bytes`].
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::open_bytes(bytes, 0)
+    }
 
-        let header: Header = state.page(1)?.as_ref().into();
-        header.validate();
-        state.header = header;
+    fn open_impl(
+        mut file: Storage,
+        base_offset: u64,
+        cache: Box<dyn PageCache>,
+        lenient: bool,
+    ) -> Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("open", base_offset).entered();
 
+        let header = read_header(&mut file, base_offset, lenient)?;
+
+        let state = DBState {
+            file,
+            base_offset,
+            pages: cache,
+            header,
+            on_page_access: None,
+            verify_checksums: false,
+        };
         Ok(Self {
-            state: Arc::new(Mutex::new(state)),
+            state: Arc::new(RwLock::new(state)),
         })
     }
 
-    pub(crate) fn btree_page(&self, page_number: u32) -> Result<BTreePage> {
-        let mut inner = self.state.lock().unwrap();
-        let page = inner.page(page_number)?;
+    /// Registers a callback that is invoked with the page number on every page
+    /// access, and whether the page was already cached. The callback runs while
+    /// the database is locked, so it must not access the database itself. It
+    /// may run on several threads at once for pages that are already cached.
+    pub fn on_page_access(&self, hook: impl Fn(u32, bool) + Send + Sync + 'static) {
+        let mut inner = self.state.write().unwrap();
+        inner.on_page_access = Some(Box::new(hook));
+    }
 
-        Ok(BTreePage::new(self.clone(), page_number, page.into()))
+    /// Rereads the header to pick up changes that another process has
+    /// written to the file. If the file has changed since it was opened or
+    /// last refreshed, the cached pages are dropped and `true` is returned.
+    /// A database held in memory never changes.
+    pub fn refresh(&self) -> Result<bool> {
+        let mut inner = self.state.write().unwrap();
+        if let Storage::Bytes(_) = inner.file {
+            return Ok(false);
+        }
+
+        let base_offset = inner.base_offset;
+        // The header was checked when the file was opened, so only problems
+        // that prevent reading it are errors now.
+        let header = read_header(&mut inner.file, base_offset, true)?;
+        if header.file_change_counter() == inner.header.file_change_counter() {
+            return Ok(false);
+        }
+        inner.header = header;
+        inner.pages.clear();
+        Ok(true)
     }
-}
 
-impl DBState {
-    pub(crate) fn page(&mut self, page_number: u32) -> Result<ArcBuf> {
-        fn inner(file: &mut File, header: &Header, page_number: u32) -> Result<ArcBuf> {
-            if !(1..=header.database_size()).contains(&page_number) {
-                return Err(anyhow!("page number out of bounds"));
+    /// Verifies the checksum of every page read from now on, as written by
+    /// SQLite's checksum VFS. Fails if the database does not reserve space for
+    /// checksums. Cached pages are dropped so that they are verified too.
+    pub fn verify_checksums(&self) -> Result<()> {
+        let mut inner = self.state.write().unwrap();
+        if inner.header.reserved_space() as usize != checksum::CHECKSUM_SIZE {
+            return Err(anyhow!(
+                "database reserves {} bytes per page, but checksums need {}",
+                inner.header.reserved_space(),
+                checksum::CHECKSUM_SIZE
+            ));
+        }
+
+        inner.verify_checksums = true;
+        inner.pages.clear();
+        Ok(())
+    }
+
+    /// Returns the details recorded in the database header.
+    pub fn info(&self) -> DbInfo {
+        let inner = self.state.read().unwrap();
+        let header = &inner.header;
+        DbInfo {
+            page_size: header.page_size(),
+            page_count: header.database_size(),
+            text_encoding: match header.text_encoding() {
+                1 => Some(TextEncoding::Utf8),
+                2 => Some(TextEncoding::Utf16le),
+                3 => Some(TextEncoding::Utf16be),
+                _ => None,
+            },
+            schema_format: header.schema_format(),
+            write_version: FileFormat::from_version(header.write_version()),
+            read_version: FileFormat::from_version(header.read_version()),
+            freelist_count: header.freelist_count(),
+            largest_root_page: header.largest_root_page(),
+            user_version: header.user_version(),
+            application_id: header.application_id(),
+        }
+    }
+
+    /// Walks the freelist and returns the number of every free page, both trunk
+    /// and leaf pages, in the order they are stored.
+    pub fn freelist_pages(&self) -> Result<Vec<u32>> {
+        Ok(self
+            .walk_freelist()?
+            .into_iter()
+            .map(|(page_number, _)| page_number)
+            .collect())
+    }
+
+    fn walk_freelist(&self) -> Result<Vec<(u32, PageKind)>> {
+        let (freelist_head, freelist_count) = {
+            let inner = self.state.read().unwrap();
+            (inner.header.freelist_head(), inner.header.freelist_count())
+        };
+
+        let mut pages = Vec::new();
+        let mut trunk = freelist_head;
+        while trunk != 0 {
+            if pages.contains(&(trunk, PageKind::FreelistTrunk)) {
+                return Err(anyhow!("freelist trunk page {trunk} is visited twice"));
             }
+            pages.push((trunk, PageKind::FreelistTrunk));
 
-            let page_size = header.page_size();
+            let mut page = self.page(trunk)?;
+            let next_trunk = page.consume::<U32>().get();
+            let leaf_count = page.consume::<U32>().get() as usize;
+            if leaf_count > page.len() / 4 {
+                return Err(anyhow!(
+                    "freelist trunk page {trunk} has too many leaves: {leaf_count}"
+                ));
+            }
+            pages.extend(
+                (0..leaf_count).map(|_| (page.consume::<U32>().get(), PageKind::FreelistLeaf)),
+            );
 
-            let mut page = vec![0; page_size as usize];
-            file.seek(SeekFrom::Start((page_number as u64 - 1) * page_size as u64))?;
-            file.read_exact(&mut page)?;
+            trunk = next_trunk;
+        }
 
-            Ok(page.into())
+        if pages.len() != freelist_count as usize {
+            return Err(anyhow!(
+                "freelist has {} pages but header declares {freelist_count}",
+                pages.len()
+            ));
         }
+        Ok(pages)
+    }
+
+    /// Classifies every page that is in use, by walking the b-tree of every
+    /// table and index along with their overflow pages, the freelist and the
+    /// pointer map. Returns the pages in order of page number. Pages that are
+    /// not reachable from any of these are left out.
+    pub fn page_map(&self) -> Result<Vec<(u32, PageKind)>> {
+        let (page_count, largest_root_page, usable_size) = {
+            let inner = self.state.read().unwrap();
+            let header = &inner.header;
+            (
+                header.database_size(),
+                header.largest_root_page(),
+                header.usable_size(),
+            )
+        };
+
+        let mut pages = BTreeMap::new();
+        let mut classify = |page_number: u32, kind: PageKind| {
+            if pages.insert(page_number, kind).is_some() {
+                return Err(anyhow!("page {page_number} is used more than once"));
+            }
+            Ok(())
+        };
 
-        let entry = self.pages.entry(page_number);
-        let page = match entry {
-            Entry::Occupied(entry) => {
-                let page = entry.into_mut();
-                if page.len() != self.header.page_size() as usize {
-                    *page = inner(&mut self.file, &self.header, page_number)?;
+        let mut roots = vec![1];
+        for schema in self.table::<Schema>()?.iter()? {
+            let schema = schema?;
+            if schema.rootpage != 0 {
+                roots.push(schema.rootpage);
+            }
+        }
+        for root in roots {
+            // Each entry is a page and its depth in the tree.
+            let mut stack = vec![(root, 0)];
+            while let Some((page_number, depth)) = stack.pop() {
+                let page = self.btree_page(page_number)?;
+                let kind = match page.page_type() {
+                    _ if page_number == 1 => PageKind::Header,
+                    BTreePageType::LeafTable => PageKind::TableLeaf,
+                    BTreePageType::InteriorTable => PageKind::TableInterior,
+                    BTreePageType::LeafIndex => PageKind::IndexLeaf,
+                    BTreePageType::InteriorIndex => PageKind::IndexInterior,
+                };
+                classify(page_number, kind)?;
+
+                for cell_index in 0..page.cell_count() {
+                    for overflow in page.cell_overflow_pages(cell_index)? {
+                        classify(overflow, PageKind::Overflow)?;
+                    }
                 }
-                page.clone()
+                for child in page.child_page_numbers()? {
+                    check_depth(depth + 1, child)?;
+                    stack.push((child, depth + 1));
+                }
+            }
+        }
+
+        for (page_number, kind) in self.walk_freelist()? {
+            classify(page_number, kind)?;
+        }
+
+        if largest_root_page != 0 {
+            for page_number in (2..=page_count).filter(|&n| is_ptrmap_page(usable_size, n)) {
+                classify(page_number, PageKind::Ptrmap)?;
             }
-            Entry::Vacant(entry) => {
-                let page = inner(&mut self.file, &self.header, page_number)?;
-                entry.insert(page).clone()
+        }
+
+        Ok(pages.into_iter().collect())
+    }
+
+    /// Whether the page belongs to the pointer map, which only auto-vacuumed
+    /// databases have.
+    pub fn is_ptrmap_page(&self, page_number: u32) -> bool {
+        let inner = self.state.read().unwrap();
+        inner.header.largest_root_page() != 0
+            && is_ptrmap_page(inner.header.usable_size(), page_number)
+    }
+
+    /// Reads any page as a b-tree page. Fails if the page is not part of a
+    /// b-tree.
+    pub fn btree_page(&self, page_number: u32) -> Result<BTreePage> {
+        let mut page = self.page(page_number)?;
+        // Cells never extend into the reserved space.
+        page.truncate(self.state.read().unwrap().header.usable_size() as usize);
+
+        BTreePage::new(self.clone(), page_number, page)
+    }
+
+    /// Reads a page. Pages that are already in memory only need a shared
+    /// lock, so that readers on other threads are not blocked; the exclusive
+    /// lock is only taken to read from the file.
+    pub(crate) fn page(&self, page_number: u32) -> Result<ArcBufSlice> {
+        if let Some(page) = self.state.read().unwrap().page_in_memory(page_number)? {
+            return Ok(page);
+        }
+        self.state.write().unwrap().read_page(page_number)
+    }
+}
+
+/// Reads and checks the database header, filling in the database size from
+/// the length of the file if the header's size is out of date.
+fn read_header(file: &mut Storage, base_offset: u64, lenient: bool) -> Result<Header> {
+    let file_len = file
+        .len()?
+        .checked_sub(base_offset)
+        .ok_or_else(|| anyhow!("database offset {base_offset} is past the end of the file"))?;
+    if file_len < HEADER_SIZE as u64 {
+        return Err(anyhow!(
+                "not a database: file is only {file_len} bytes, which is too short for the {HEADER_SIZE}-byte header"
+            ));
+    }
+
+    let mut header = match file {
+        Storage::File(file) => {
+            let mut header_bytes = [0; HEADER_SIZE];
+            file.seek(SeekFrom::Start(base_offset))?;
+            file.read_exact(&mut header_bytes)?;
+            Header::from(&header_bytes[..])
+        }
+        Storage::Bytes(bytes) => {
+            let start = base_offset as usize;
+            Header::from(&bytes[start..start + HEADER_SIZE])
+        }
+    };
+    header.validate(lenient)?;
+
+    if !header.database_size_is_valid() {
+        // Older versions of SQLite did not keep the size up to date.
+        header.set_database_size((file_len / header.page_size() as u64) as u32);
+    }
+
+    let expected_len = header.database_size() as u64 * header.page_size() as u64;
+    if file_len < expected_len {
+        return Err(anyhow!(
+                "database file is truncated: header declares {expected_len} bytes but file is only {file_len} bytes"
+            ));
+    }
+
+    Ok(header)
+}
+
+/// The pointer map starts on page 2, and each of its pages describes the
+/// pages up to the next one.
+fn is_ptrmap_page(usable_size: u32, page_number: u32) -> bool {
+    let interval = usable_size / 5 + 1;
+    page_number >= 2 && (page_number - 2).is_multiple_of(interval)
+}
+
+impl Storage {
+    fn len(&self) -> Result<u64> {
+        match self {
+            Self::File(file) => Ok(file.metadata()?.len()),
+            Self::Bytes(bytes) => Ok(bytes.len() as u64),
+        }
+    }
+}
+
+impl DBState {
+    fn page_offset(&self, page_number: u32) -> Result<u64> {
+        if !(1..=self.header.database_size()).contains(&page_number) {
+            return Err(anyhow!("page number out of bounds"));
+        }
+        Ok(self.base_offset + (page_number as u64 - 1) * self.header.page_size() as u64)
+    }
+
+    fn verify(&self, page_number: u32, page: &[u8]) -> Result<()> {
+        if self.verify_checksums && !checksum::verify(page) {
+            return Err(anyhow!("checksum mismatch on page {page_number}"));
+        }
+        Ok(())
+    }
+
+    fn accessed(&self, page_number: u32, cached: bool) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(page_number, cached, "page access");
+
+        if let Some(on_page_access) = &self.on_page_access {
+            on_page_access(page_number, cached);
+        }
+    }
+
+    /// Returns the page if it can be found without reading the file.
+    fn page_in_memory(&self, page_number: u32) -> Result<Option<ArcBufSlice>> {
+        let offset = self.page_offset(page_number)?;
+        let page = match &self.file {
+            // A page of another size was cached under a different header, and
+            // is read again.
+            Storage::File(_) => match self.pages.get(page_number) {
+                Some(page) if page.len() == self.header.page_size() as usize => {
+                    self.accessed(page_number, true);
+                    ArcBufSlice::from(page)
+                }
+                _ => return Ok(None),
+            },
+            // Pages of an in-memory image are never copied into the cache.
+            Storage::Bytes(bytes) => {
+                // The image was checked to hold every page when it was opened.
+                let start = offset as usize;
+                let page_size = self.header.page_size() as usize;
+                let page = ArcBufSlice::new(bytes.clone(), start, start + page_size);
+                self.verify(page_number, &page)?;
+                self.accessed(page_number, false);
+                page
             }
         };
+        Ok(Some(page))
+    }
+
+    /// Reads the page from the file into the cache, unless another reader
+    /// already has.
+    fn read_page(&mut self, page_number: u32) -> Result<ArcBufSlice> {
+        if let Some(page) = self.page_in_memory(page_number)? {
+            return Ok(page);
+        }
+
+        let offset = self.page_offset(page_number)?;
+        let Storage::File(file) = &mut self.file else {
+            unreachable!("in-memory pages are always found");
+        };
+        let mut page = vec![0; self.header.page_size() as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut page)?;
+        self.verify(page_number, &page)?;
 
-        Ok(page)
+        let page = ArcBuf::from(page);
+        self.pages.insert(page_number, page.clone());
+        self.accessed(page_number, false);
+        Ok(ArcBufSlice::from(page))
+    }
+}
+
+impl fmt::Debug for DBState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DBState")
+            .field("file", &self.file)
+            .field("header", &self.header)
+            .finish_non_exhaustive()
     }
 }
 
@@ -92,14 +549,40 @@ impl fmt::Debug for DB {
 
 #[cfg(test)]
 mod tests {
-    use crate::physical::btree::BTreePageType;
+    use std::{
+        io::Write,
+        mem,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use serde::Deserialize;
+    use squeak_macros::Table;
+
+    use crate::{
+        physical::{btree::BTreePageType, cache::NoCache},
+        schema::{record::SerialValue, serialization::row_id, SchemaType, Table, WithRowId},
+    };
 
     use super::*;
 
+    #[derive(Debug, Deserialize, Table)]
+    struct Numbers {
+        #[table(row_id)]
+        #[serde(with = "row_id")]
+        _id: i64,
+        _square: i64,
+        _name: String,
+    }
+
     #[test]
     fn test_open() {
         let db = DB::open("examples/empty.db").unwrap();
-        assert_eq!(db.state.lock().unwrap().header.page_size(), 4096);
+        assert_eq!(db.state.read().unwrap().header.page_size(), 4096);
     }
 
     #[test]
@@ -109,7 +592,698 @@ mod tests {
         let root = db.btree_page(1).unwrap();
         assert_eq!(root.page_type(), BTreePageType::LeafTable);
 
-        let cell = root.leaf_table_cell(0);
+        let cell = root.leaf_table_cell(0).unwrap();
         assert_eq!(cell.0, 1);
+
+        assert_eq!(root.page_number(), 1);
+        assert_eq!(root.raw().len(), 4096);
+        assert!(root.raw().starts_with(b"SQLite format 3\0"));
+    }
+
+    #[test]
+    fn test_read_non_btree_page() {
+        let db = DB::open("examples/freelist.db").unwrap();
+        let err = db.btree_page(267).unwrap_err();
+        assert!(err.to_string().contains("not a b-tree page"), "{err}");
+    }
+
+    #[test]
+    fn test_page_access_hook() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let accesses = Arc::new(Mutex::new(Vec::new()));
+        let hook_accesses = accesses.clone();
+        db.on_page_access(move |page_number, cached| {
+            hook_accesses.lock().unwrap().push((page_number, cached));
+        });
+
+        table.get(1).unwrap().unwrap();
+        let first = mem::take(&mut *accesses.lock().unwrap());
+        // The table's b-tree is three levels deep.
+        assert_eq!(first.len(), 3);
+        assert!(first.iter().all(|&(_, cached)| !cached));
+
+        table.get(1).unwrap().unwrap();
+        let second = mem::take(&mut *accesses.lock().unwrap());
+        assert_eq!(
+            second,
+            first
+                .iter()
+                .map(|&(page_number, _)| (page_number, true))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_concurrent_cache_hits() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        db.btree_page(2).unwrap();
+
+        // Each cache hit waits until both threads are inside the hook at the
+        // same time, which would never happen if hits took an exclusive lock.
+        let waiting = Arc::new(AtomicUsize::new(0));
+        let hook_waiting = waiting.clone();
+        db.on_page_access(move |_, cached| {
+            if !cached {
+                return;
+            }
+            hook_waiting.fetch_add(1, Ordering::SeqCst);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while hook_waiting.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                thread::yield_now();
+            }
+        });
+
+        let threads = (0..2)
+            .map(|_| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    let start = Instant::now();
+                    db.btree_page(2).unwrap();
+                    start.elapsed()
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            assert!(thread.join().unwrap() < Duration::from_secs(5));
+        }
+        assert_eq!(waiting.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DB>();
+
+        // Keeps only a few pages, so that pages are dropped from the cache
+        // while other threads are still reading them.
+        #[derive(Default)]
+        struct TinyCache(BTreeMap<u32, ArcBuf>);
+
+        impl PageCache for TinyCache {
+            fn get(&self, page_number: u32) -> Option<ArcBuf> {
+                self.0.get(&page_number).cloned()
+            }
+
+            fn insert(&mut self, page_number: u32, page: ArcBuf) {
+                if self.0.len() >= 3 {
+                    self.0.pop_first();
+                }
+                self.0.insert(page_number, page);
+            }
+
+            fn clear(&mut self) {
+                self.0.clear();
+            }
+        }
+
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "numbers")]
+        struct Squares {
+            #[table(row_id)]
+            id: i64,
+            square: i64,
+            _name: String,
+        }
+
+        let db = DB::open_with_cache("examples/numbers.db", TinyCache::default()).unwrap();
+        let threads = (0..8)
+            .map(|thread| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    let table = db.table::<Squares>().unwrap();
+                    for row in table.iter().unwrap() {
+                        let row = row.unwrap();
+                        assert_eq!(row.square, row.id * row.id);
+                    }
+                    for id in (thread + 1..=3000).step_by(97) {
+                        assert_eq!(table.get(id).unwrap().unwrap().square, id * id);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_open_truncated() {
+        let bytes = std::fs::read("examples/string_index.db").unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &bytes[..bytes.len() - 100]).unwrap();
+
+        let err = DB::open(file.path().to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{err}");
+    }
+
+    #[test]
+    fn test_malformed_cell() {
+        let bytes = std::fs::read("examples/empty.db").unwrap();
+        let cell_pointer = 100 + 8;
+        assert_eq!(bytes.len(), 4096 * 2);
+
+        // Point the only schema cell at the last two bytes of page 1, which
+        // start a varint that never ends.
+        let mut truncated_varint = bytes.clone();
+        truncated_varint[cell_pointer..cell_pointer + 2].copy_from_slice(&4094u16.to_be_bytes());
+        truncated_varint[4094..4096].fill(0x81);
+        // Claim a payload far larger than the page.
+        let mut long_payload = bytes;
+        let cell = u16::from_be_bytes([long_payload[cell_pointer], long_payload[cell_pointer + 1]]);
+        long_payload[cell as usize..cell as usize + 2].copy_from_slice(&[0xff, 0x7f]);
+
+        for bytes in [truncated_varint, long_payload] {
+            let db = DB::open_bytes(bytes, 0).unwrap();
+            let schema = db.table::<crate::schema::Schema>().unwrap();
+            let err = schema.iter().unwrap().next().unwrap().unwrap_err();
+            assert!(
+                err.to_string().contains("malformed cell 0 on page 1"),
+                "{err}"
+            );
+            let mut cursor = schema.cursor().unwrap();
+            assert!(cursor.first().unwrap());
+            assert!(cursor.current().is_err());
+            assert!(cursor.seek(1).is_err());
+        }
+    }
+
+    #[test]
+    fn test_freelist_pages() {
+        let db = DB::open("examples/freelist.db").unwrap();
+        let mut pages = db.freelist_pages().unwrap();
+
+        // The first trunk page comes first.
+        assert_eq!(pages[0], 267);
+        assert_eq!(
+            pages.len(),
+            db.state.read().unwrap().header.freelist_count() as usize
+        );
+        pages.sort();
+        assert_eq!(pages, (23..=307).collect::<Vec<_>>());
+
+        let db = DB::open("examples/empty.db").unwrap();
+        assert_eq!(db.freelist_pages().unwrap(), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_page_map() {
+        let db = DB::open("examples/empty.db").unwrap();
+        assert_eq!(
+            db.page_map().unwrap(),
+            [(1, PageKind::Header), (2, PageKind::TableLeaf)]
+        );
+
+        // An auto-vacuumed database with overflowing rows and index entries,
+        // and a dropped table.
+        let db = DB::open("examples/page_map.db").unwrap();
+        let overflow = [6, 7, 8, 9, 10, 11, 12, 13].map(|page| (page, PageKind::Overflow));
+        let mut expected = vec![
+            (1, PageKind::Header),
+            (2, PageKind::Ptrmap),
+            (3, PageKind::TableLeaf),
+            (4, PageKind::IndexLeaf),
+            (5, PageKind::FreelistLeaf),
+            (14, PageKind::FreelistTrunk),
+            (15, PageKind::FreelistLeaf),
+        ];
+        expected.extend(overflow);
+        expected.sort_by_key(|&(page, _)| page);
+        assert_eq!(db.page_map().unwrap(), expected);
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        let map = db.page_map().unwrap();
+        assert_eq!(map.len(), db.info().page_count as usize);
+        assert!(map.contains(&(2, PageKind::TableInterior)));
+    }
+
+    #[test]
+    fn test_cell_overflow_pages() {
+        let db = DB::open("examples/page_map.db").unwrap();
+        let docs = db.btree_page(3).unwrap();
+        let chains = (0..docs.cell_count())
+            .map(|cell_index| docs.cell_overflow_pages(cell_index).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(chains, [vec![13, 6, 7, 8], vec![], vec![9, 10, 11], vec![]]);
+        let titles = db.btree_page(4).unwrap();
+        assert_eq!(titles.cell_overflow_pages(3).unwrap(), [12]);
+        assert!(docs.cell_overflow_pages(4).is_err());
+
+        let map = db.page_map().unwrap();
+        for page in chains.iter().flatten() {
+            assert!(map.contains(&(*page, PageKind::Overflow)));
+        }
+
+        // Chains that end too early or run on are reported.
+        let bytes = std::fs::read("examples/page_map.db").unwrap();
+        let page_size = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+        let with_next = |page: usize, next: u32| {
+            let mut bytes = bytes.clone();
+            let start = (page - 1) * page_size;
+            bytes[start..start + 4].copy_from_slice(&next.to_be_bytes());
+            DB::open_bytes(bytes, 0).unwrap()
+        };
+        let db = with_next(7, 0);
+        assert!(db.btree_page(3).unwrap().cell_overflow_pages(0).is_err());
+        let db = with_next(8, 9);
+        assert!(db.btree_page(3).unwrap().cell_overflow_pages(0).is_err());
+    }
+
+    #[test]
+    fn test_no_cache() {
+        let db = DB::open_with_cache("examples/numbers.db", NoCache).unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let accesses = Arc::new(Mutex::new(Vec::new()));
+        let hook_accesses = accesses.clone();
+        db.on_page_access(move |page_number, cached| {
+            hook_accesses.lock().unwrap().push((page_number, cached));
+        });
+
+        for _ in 0..2 {
+            table.get(1).unwrap().unwrap();
+            let accesses = mem::take(&mut *accesses.lock().unwrap());
+            assert_eq!(accesses.len(), 3);
+            assert!(accesses.iter().all(|&(_, cached)| !cached));
+        }
+        assert_eq!(table.iter().unwrap().count(), 3000);
+    }
+
+    #[test]
+    fn test_stale_page_size() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        // As if page 2 had been cached before the page size changed.
+        let stale = ArcBuf::from(vec![0; 4096]);
+        db.state.write().unwrap().pages.insert(2, stale);
+
+        assert_eq!(table.get(12).unwrap().unwrap()._square, 144);
+        let state = db.state.read().unwrap();
+        assert_eq!(state.pages.get(2).unwrap().len(), 512);
+    }
+
+    #[test]
+    fn test_verify_checksums() {
+        #[derive(Debug, Deserialize, Table)]
+        struct Numbers {
+            #[table(row_id)]
+            #[serde(with = "row_id")]
+            id: i64,
+            square: i64,
+            _name: String,
+        }
+
+        let db = DB::open("examples/checksums.db").unwrap();
+        db.verify_checksums().unwrap();
+        let rows = db.table::<Numbers>().unwrap().iter().unwrap();
+        for row in rows {
+            let row = row.unwrap();
+            assert_eq!(row.square, row.id * row.id);
+        }
+
+        let mut bytes = std::fs::read("examples/checksums.db").unwrap();
+        // Change the text of a row on the last page.
+        let last_page = bytes.len() - 1024;
+        let text = bytes[last_page..]
+            .windows(6)
+            .position(|window| window == b"number")
+            .unwrap();
+        bytes[last_page + text] = b'N';
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &bytes).unwrap();
+
+        let db = DB::open(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(db.table::<Numbers>().unwrap().iter().unwrap().count(), 200);
+        db.verify_checksums().unwrap();
+        let err = db
+            .table::<Numbers>()
+            .unwrap()
+            .iter()
+            .unwrap()
+            .find_map(Result::err)
+            .unwrap();
+        assert!(err.to_string().contains("checksum mismatch"), "{err}");
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        assert!(db.verify_checksums().is_err());
+    }
+
+    #[test]
+    fn test_open_lenient() {
+        let mut bytes = std::fs::read("examples/empty.db").unwrap();
+        // The maximum embedded payload fraction must be 64.
+        bytes[21] = 63;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        std::fs::write(path, &bytes).unwrap();
+
+        let err = DB::open(path).unwrap_err();
+        assert!(err.to_string().contains("payload fractions"), "{err}");
+
+        let db = DB::open_lenient(path).unwrap();
+        assert_eq!(
+            db.btree_page(1).unwrap().page_type(),
+            BTreePageType::LeafTable
+        );
+
+        std::fs::write(path, [0; 1024]).unwrap();
+        let err = DB::open_lenient(path).unwrap_err();
+        assert!(err.to_string().contains("not a SQLite database"), "{err}");
+    }
+
+    #[test]
+    fn test_open_large_pages() {
+        #[derive(Debug, Deserialize, Table)]
+        struct Numbers {
+            #[table(row_id)]
+            #[serde(with = "row_id")]
+            id: i64,
+            square: i64,
+            name: String,
+        }
+
+        let db = DB::open("examples/large_pages.db").unwrap();
+        assert_eq!(db.state.read().unwrap().header.page_size(), 65536);
+
+        let table = db.table::<Numbers>().unwrap();
+        assert_eq!(table.iter().unwrap().count(), 5000);
+        for id in [1, 2500, 5000] {
+            let row = table.get(id).unwrap().unwrap();
+            assert_eq!((row.id, row.square), (id, id * id));
+            assert_eq!(row.name, format!("number {id}"));
+        }
+    }
+
+    #[test]
+    fn test_large_pages_interior_index() {
+        use crate::schema::WithoutRowId;
+
+        #[derive(Debug, Deserialize, Table)]
+        #[table(without_rowid)]
+        struct Words {
+            #[table(primary_key)]
+            word: String,
+            _length: i64,
+        }
+
+        #[derive(Debug, Deserialize, Table)]
+        struct Unused {
+            #[table(row_id)]
+            #[serde(with = "row_id")]
+            _id: i64,
+        }
+
+        // The words are long enough that the b-tree has an interior page.
+        let db = DB::open("examples/large_index.db").unwrap();
+        let table = db.table::<Words>().unwrap();
+        assert_eq!(table.row_count().unwrap(), 200);
+
+        let words = table
+            .iter_without_row_id()
+            .unwrap()
+            .map(|row| row.map(|row| row.word))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(words.len(), 200);
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        for (number, word) in words.iter().enumerate() {
+            assert!(word.starts_with(&format!("{number:04}")), "{word}");
+        }
+
+        let last = table.get_by_key(&(words[199].clone(),)).unwrap().unwrap();
+        assert_eq!(last.word, words[199]);
+        assert_eq!(table.max_key().unwrap().unwrap().word, words[199]);
+
+        // An empty page stores the start of its cell content area as 0,
+        // which means 65536 here.
+        let unused = db.table::<Unused>().unwrap();
+        assert_eq!(unused.iter().unwrap().count(), 0);
+        assert_eq!(unused.row_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_open_too_short() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        for len in [0, 50] {
+            std::fs::write(file.path(), vec![0; len]).unwrap();
+            let err = DB::open(file.path().to_str().unwrap()).unwrap_err();
+            assert!(err.to_string().contains("not a database"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_open_without_database_size() {
+        let mut bytes = std::fs::read("examples/numbers.db").unwrap();
+        // Clear the in-header database size.
+        bytes[28..32].fill(0);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &bytes).unwrap();
+
+        let db = DB::open(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            db.state.read().unwrap().header.database_size() as usize,
+            bytes.len() / 512
+        );
+        assert_eq!(db.table::<Numbers>().unwrap().iter().unwrap().count(), 3000);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::copy("examples/join.db", file.path()).unwrap();
+        let db = DB::open(file.path().to_str().unwrap()).unwrap();
+        let total = |db: &DB| {
+            let order = db.select("orders").unwrap().nth(3).unwrap().unwrap();
+            order.get("total").and_then(SerialValue::as_i64)
+        };
+        assert_eq!(total(&db), Some(40));
+        assert!(!db.refresh().unwrap());
+
+        // Another process changes the total of order 4.
+        std::fs::copy("examples/join_changed.db", file.path()).unwrap();
+        assert_eq!(total(&db), Some(40));
+        assert!(db.refresh().unwrap());
+        assert_eq!(total(&db), Some(41));
+        assert!(!db.refresh().unwrap());
+
+        let db = DB::open_bytes(std::fs::read("examples/join.db").unwrap(), 0).unwrap();
+        assert!(!db.refresh().unwrap());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(&std::fs::read("examples/numbers.db").unwrap())
+            .unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let db = DB::from_file(file).unwrap();
+        let tables = db.tables().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name, "numbers");
+        assert_eq!(tables[0].row_count, 3000);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        // Hands out a few bytes at a time, like a pipe.
+        struct Trickle(std::vec::IntoIter<u8>);
+
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let mut len = 0;
+                for (byte, value) in buf.iter_mut().take(7).zip(&mut self.0) {
+                    *byte = value;
+                    len += 1;
+                }
+                Ok(len)
+            }
+        }
+
+        let bytes = std::fs::read("examples/empty.db").unwrap();
+        let db = DB::from_reader(Trickle(bytes.into_iter())).unwrap();
+        let tables = db.tables().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name, "empty");
+
+        assert!(DB::from_reader(&[0u8; 50][..]).is_err());
+    }
+
+    #[test]
+    fn test_open_bytes_with_offset() {
+        let mut bytes = vec![0xaa; 1024];
+        bytes.extend(std::fs::read("examples/empty.db").unwrap());
+
+        let db = DB::open_bytes(bytes.clone(), 1024).unwrap();
+        let tables = db.tables().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name, "empty");
+        assert_eq!(tables[0].row_count, 0);
+
+        assert!(DB::open_bytes(bytes.clone(), 0).is_err());
+        assert!(DB::open_bytes(bytes, 100_000).is_err());
+
+        let mut bytes = vec![0; 1024];
+        bytes.extend(std::fs::read("examples/numbers.db").unwrap());
+        let db = DB::open_bytes(bytes, 1024).unwrap();
+        assert_eq!(db.table::<Numbers>().unwrap().iter().unwrap().count(), 3000);
+    }
+
+    #[test]
+    fn test_open_bytes_without_copying() {
+        let image: ArcBuf = std::fs::read("examples/numbers.db").unwrap().into();
+        let image_range = image.as_ptr_range();
+        let db = DB::open_bytes(image.clone(), 0).unwrap();
+
+        let table = db.table::<Numbers>().unwrap();
+        let mut count = 0;
+        for entry in table
+            .rootpage()
+            .unwrap()
+            .into_table_entries_range(None..None)
+            .unwrap()
+        {
+            let (_, record) = entry.unwrap();
+            assert!(image_range.contains(&record.as_ptr()));
+            count += 1;
+        }
+        assert_eq!(count, 3000);
+
+        // Nothing was copied into the page cache either.
+        assert!(db.state.read().unwrap().pages.get(1).is_none());
+    }
+
+    #[derive(Debug, Deserialize, Table)]
+    #[table(name = "t")]
+    struct Named {
+        #[table(row_id)]
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn test_read_reserved_space() {
+        let db = DB::open("examples/reserved.db").unwrap();
+        assert_eq!(db.state.read().unwrap().header.reserved_space(), 32);
+
+        // Page 1 holds both the database header and cells that end just
+        // before the reserved space.
+        let names = db
+            .tables()
+            .unwrap()
+            .into_iter()
+            .map(|table| table.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["t", "t2", "t3"]);
+
+        let rows = db
+            .table::<Named>()
+            .unwrap()
+            .iter()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows.len(), 300);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.id, i as i64 + 1);
+            assert_eq!(row.name, format!("name {}", i + 1));
+        }
+    }
+
+    #[test]
+    fn test_info() {
+        let db = DB::open("examples/empty.db").unwrap();
+        assert_eq!(
+            db.info(),
+            DbInfo {
+                page_size: 4096,
+                page_count: 2,
+                text_encoding: Some(TextEncoding::Utf8),
+                schema_format: 4,
+                write_version: FileFormat::Legacy,
+                read_version: FileFormat::Legacy,
+                freelist_count: 0,
+                largest_root_page: 0,
+                user_version: 0,
+                application_id: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_is_ptrmap_page() {
+        let ptrmap_pages = |usable_size| {
+            (1..=2000)
+                .filter(|&n| is_ptrmap_page(usable_size, n))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ptrmap_pages(512),
+            [
+                2, 105, 208, 311, 414, 517, 620, 723, 826, 929, 1032, 1135, 1238, 1341, 1444, 1547,
+                1650, 1753, 1856, 1959
+            ]
+        );
+        assert_eq!(ptrmap_pages(4096), [2, 822, 1642]);
+        // Reserved space shrinks the pointer map pages.
+        assert_eq!(ptrmap_pages(4096 - 32)[1], 815);
+
+        let db = DB::open("examples/page_map.db").unwrap();
+        assert_eq!(db.info().largest_root_page, 4);
+        assert!(db.is_ptrmap_page(2));
+        assert!(!db.is_ptrmap_page(3));
+        let db = DB::open("examples/empty.db").unwrap();
+        assert!(!db.is_ptrmap_page(2));
+    }
+
+    #[test]
+    fn test_bogus_cell_pointer() {
+        let mut bytes = std::fs::read("examples/numbers.db").unwrap();
+        let page_number = (2..)
+            .find(|&page_number| bytes[(page_number - 1) * 512] == 0x0d)
+            .unwrap();
+        // Point the first cell into the page header.
+        let pointer = (page_number - 1) * 512 + 8;
+        bytes[pointer..pointer + 2].copy_from_slice(&2u16.to_be_bytes());
+
+        let db = DB::open_bytes(bytes, 0).unwrap();
+        let err = db.btree_page(page_number as u32).unwrap_err();
+        assert!(
+            err.to_string().contains("outside the cell content area"),
+            "{err}"
+        );
+
+        let rows = db
+            .table::<Numbers>()
+            .unwrap()
+            .iter()
+            .unwrap()
+            .collect::<Result<Vec<_>>>();
+        assert!(rows.is_err());
+    }
+
+    #[test]
+    fn test_interior_cycle() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let root = db.table::<Numbers>().unwrap().rootpage().unwrap();
+        assert_eq!(root.page_type(), BTreePageType::InteriorTable);
+        let root = root.page_number();
+
+        // Make the first child of the root point back at the root.
+        let mut bytes = std::fs::read("examples/numbers.db").unwrap();
+        let page_start = (root as usize - 1) * 512;
+        let cell = u16::from_be_bytes([bytes[page_start + 12], bytes[page_start + 13]]) as usize;
+        bytes[page_start + cell..page_start + cell + 4].copy_from_slice(&root.to_be_bytes());
+
+        let db = DB::open_bytes(bytes, 0).unwrap();
+        let table = db.table::<Numbers>().unwrap();
+        let err = table.iter().unwrap().find_map(Result::err).unwrap();
+        assert!(err.to_string().contains("cycle"), "{err}");
+        assert!(table.row_count().is_err());
+        assert!(table.cursor().unwrap().first().is_err());
+        // Rows outside the cycle can still be reached.
+        assert!(table.cursor().unwrap().last().unwrap());
     }
 }
@@ -1,4 +1,8 @@
-use zerocopy::{big_endian::U32, little_endian, FromBytes, FromZeroes};
+use anyhow::{anyhow, Result};
+use zerocopy::{
+    big_endian::{U16, U32},
+    FromBytes,
+};
 
 const HEADER_STRING: [u8; 16] = *b"SQLite format 3\0";
 pub const HEADER_SIZE: usize = 100;
@@ -17,8 +21,8 @@ pub const HEADER_SIZE: usize = 100;
 pub struct Header {
     /// The header string: "SQLite format 3\0"
     header_string: [u8; 16],
-    /// The database page size as a multiple of 256 bytes. Must be a power of two between 512 and 65536 bytes inclusive.
-    page_size: little_endian::U16,
+    /// The database page size in bytes. Must be a power of two between 512 and 32768 inclusive, or the value 1 representing a page size of 65536.
+    page_size: U16,
     /// File format write version. 1 for legacy; 2 for WAL.
     write_version: u8,
     /// File format read version. 1 for legacy; 2 for WAL.
@@ -35,18 +39,32 @@ pub struct Header {
     file_change_counter: U32,
     /// Size of the database file in pages. The "in-header database size".
     database_size: U32,
-    // The rest of the header is irrelevant for our purposes.
-}
-
-impl Default for Header {
-    fn default() -> Self {
-        Self {
-            header_string: HEADER_STRING,
-            page_size: 2.into(),
-            database_size: 1.into(),
-            ..FromZeroes::new_zeroed()
-        }
-    }
+    /// Page number of the first freelist trunk page.
+    freelist_head: U32,
+    /// Total number of freelist pages.
+    freelist_count: U32,
+    /// The schema cookie.
+    schema_cookie: U32,
+    /// The schema format number. Supported schema formats are 1, 2, 3, and 4.
+    schema_format: U32,
+    /// Default page cache size.
+    default_cache_size: U32,
+    /// The page number of the largest root b-tree page when in auto-vacuum or incremental-vacuum modes, or zero otherwise.
+    largest_root_page: U32,
+    /// The database text encoding. 1 for UTF-8; 2 for UTF-16le; 3 for UTF-16be.
+    text_encoding: U32,
+    /// The "user version" as read and set by the user_version pragma.
+    user_version: U32,
+    /// True (non-zero) for incremental-vacuum mode. False (zero) otherwise.
+    incremental_vacuum: U32,
+    /// The "Application ID" set by PRAGMA application_id.
+    application_id: U32,
+    /// Reserved for expansion. Must be zero.
+    reserved: [u8; 20],
+    /// The version-valid-for number.
+    version_valid_for: U32,
+    /// SQLITE_VERSION_NUMBER
+    sqlite_version: U32,
 }
 
 impl<'a> From<&'a [u8]> for Header {
@@ -56,26 +74,123 @@ impl<'a> From<&'a [u8]> for Header {
 }
 
 impl Header {
-    pub(crate) fn validate(&self) {
-        assert_eq!(self.header_string, HEADER_STRING);
+    /// Checks that the header describes a database that can be read. When
+    /// `lenient` is set, problems that do not prevent reading the file are
+    /// logged as warnings instead of returned as errors.
+    pub(crate) fn validate(&self, lenient: bool) -> Result<()> {
+        if self.header_string != HEADER_STRING {
+            return Err(anyhow!("not a SQLite database: invalid header string"));
+        }
 
         let page_size = self.page_size();
-        assert!(page_size.is_power_of_two());
-        assert!((512..=65536).contains(&page_size));
+        if !page_size.is_power_of_two() || !(512..=65536).contains(&page_size) {
+            return Err(anyhow!("invalid page size: {page_size}"));
+        }
+
+        if self.write_version > 2 || self.read_version > 2 {
+            return Err(anyhow!(
+                "unsupported file format version: write {}, read {}",
+                self.write_version,
+                self.read_version
+            ));
+        }
+
+        let mut warnings = Vec::new();
+        if self.write_version != 1 || self.read_version != 1 {
+            warnings.push("database is in WAL mode, so recent changes may be missing".to_owned());
+        }
+        let payload_fractions = (
+            self.max_payload_fraction,
+            self.min_payload_fraction,
+            self.leaf_payload_fraction,
+        );
+        if payload_fractions != (64, 32, 32) {
+            warnings.push(format!(
+                "unusual payload fractions: max {}, min {}, leaf {}",
+                payload_fractions.0, payload_fractions.1, payload_fractions.2
+            ));
+        }
 
-        assert_eq!(self.write_version, 1);
-        assert_eq!(self.read_version, 1);
-        assert_eq!(self.reserved_space, 0);
-        assert_eq!(self.max_payload_fraction, 64);
-        assert_eq!(self.min_payload_fraction, 32);
-        assert_eq!(self.leaf_payload_fraction, 32);
+        for warning in warnings {
+            if !lenient {
+                return Err(anyhow!(warning));
+            }
+            log::warn!("{warning}");
+        }
+        Ok(())
     }
 
     pub(crate) fn page_size(&self) -> u32 {
-        self.page_size.get() as u32 * 256
+        match self.page_size.get() {
+            1 => 65536,
+            page_size => page_size as u32,
+        }
+    }
+
+    pub(crate) fn reserved_space(&self) -> u8 {
+        self.reserved_space
+    }
+
+    /// The page size, excluding the reserved space at the end of each page.
+    pub(crate) fn usable_size(&self) -> u32 {
+        self.page_size() - self.reserved_space as u32
+    }
+
+    /// Incremented by SQLite whenever it writes to the file, other than in
+    /// WAL mode.
+    pub(crate) fn file_change_counter(&self) -> u32 {
+        self.file_change_counter.get()
     }
 
     pub(crate) fn database_size(&self) -> u32 {
         self.database_size.get()
     }
+
+    /// The in-header database size is only valid if it is non-zero and the
+    /// file was last written by a version of SQLite that maintains it.
+    pub(crate) fn database_size_is_valid(&self) -> bool {
+        self.database_size.get() != 0 && self.file_change_counter == self.version_valid_for
+    }
+
+    pub(crate) fn set_database_size(&mut self, pages: u32) {
+        self.database_size = pages.into();
+    }
+
+    pub(crate) fn freelist_head(&self) -> u32 {
+        self.freelist_head.get()
+    }
+
+    pub(crate) fn freelist_count(&self) -> u32 {
+        self.freelist_count.get()
+    }
+
+    /// Non-zero if the database is auto-vacuumed, and so has pointer map
+    /// pages.
+    pub(crate) fn largest_root_page(&self) -> u32 {
+        self.largest_root_page.get()
+    }
+
+    pub(crate) fn write_version(&self) -> u8 {
+        self.write_version
+    }
+
+    pub(crate) fn read_version(&self) -> u8 {
+        self.read_version
+    }
+
+    pub(crate) fn schema_format(&self) -> u32 {
+        self.schema_format.get()
+    }
+
+    pub(crate) fn text_encoding(&self) -> u32 {
+        self.text_encoding.get()
+    }
+
+    pub(crate) fn user_version(&self) -> u32 {
+        self.user_version.get()
+    }
+
+    pub(crate) fn application_id(&self) -> u32 {
+        self.application_id.get()
+    }
 }
@@ -1,5 +1,7 @@
-pub(crate) mod btree;
+pub mod btree;
 pub(crate) mod buf;
+pub mod cache;
+pub(crate) mod checksum;
 pub mod db;
 pub(crate) mod header;
 pub(crate) mod varint;
@@ -1,9 +1,15 @@
-pub fn read(bytes: &[u8]) -> (u64, usize) {
+use anyhow::{anyhow, Result};
+
+/// Reads a varint from the start of `bytes`, returning its value and length.
+/// Fails if the varint runs past the end of `bytes`.
+pub fn read(bytes: &[u8]) -> Result<(u64, usize)> {
     let mut result = 0;
     let mut i = 0;
 
     loop {
-        let byte = bytes[i];
+        let byte = *bytes
+            .get(i)
+            .ok_or_else(|| anyhow!("varint runs past the end of the buffer"))?;
 
         if i >= 8 {
             result <<= 8;
@@ -20,7 +26,41 @@ pub fn read(bytes: &[u8]) -> (u64, usize) {
         i += 1;
     }
 
-    (result, i + 1)
+    Ok((result, i + 1))
+}
+
+pub fn write(value: u64, buf: &mut Vec<u8>) -> usize {
+    let mut bytes = [0; 9];
+
+    if value >> 56 != 0 {
+        bytes[8] = value as u8;
+        let mut rest = value >> 8;
+        for byte in bytes[..8].iter_mut().rev() {
+            *byte = rest as u8 | 0x80;
+            rest >>= 7;
+        }
+
+        buf.extend_from_slice(&bytes);
+        return 9;
+    }
+
+    let mut start = 9;
+    let mut rest = value;
+    loop {
+        start -= 1;
+        bytes[start] = rest as u8 & 0x7f;
+        if start != 8 {
+            bytes[start] |= 0x80;
+        }
+
+        rest >>= 7;
+        if rest == 0 {
+            break;
+        }
+    }
+
+    buf.extend_from_slice(&bytes[start..]);
+    9 - start
 }
 
 #[cfg(test)]
@@ -29,9 +69,30 @@ mod tests {
 
     #[test]
     fn test_read_varint() {
-        assert_eq!(read(&[0x01]), (1, 1));
-        assert_eq!(read(&[0x80, 0x40]), (64, 2));
-        assert_eq!(read(&[0x80; 9]), (128, 9));
-        assert_eq!(read(&[0xff; 9]), (u64::MAX, 9));
+        assert_eq!(read(&[0x01]).unwrap(), (1, 1));
+        assert_eq!(read(&[0x80, 0x40]).unwrap(), (64, 2));
+        assert_eq!(read(&[0x80; 9]).unwrap(), (128, 9));
+        assert_eq!(read(&[0xff; 9]).unwrap(), (u64::MAX, 9));
+    }
+
+    #[test]
+    fn test_read_truncated_varint() {
+        assert!(read(&[]).is_err());
+        assert!(read(&[0x80, 0x80]).is_err());
+        assert!(read(&[0xff; 8]).is_err());
+    }
+
+    #[test]
+    fn test_write_varint() {
+        for value in [0, 1, 64, 127, 128, 16383, 16384, 1 << 56, u64::MAX] {
+            let mut buf = Vec::new();
+            let len = write(value, &mut buf);
+            assert_eq!(buf.len(), len);
+            assert_eq!(read(&buf).unwrap(), (value, len));
+        }
+
+        let mut buf = Vec::new();
+        write(64, &mut buf);
+        assert_eq!(buf, [0x40]);
     }
 }
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use arrow_array::{
+    builder::{BinaryBuilder, Float64Builder, Int64Builder, StringBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+use crate::physical::btree::iter::BTreeTableEntries;
+
+use super::{
+    record::{Record, SerialValue},
+    sql::Affinity,
+    TableHandle, WithRowId,
+};
+
+/// The rows of a table as Arrow record batches. See
+/// [`TableHandle::to_record_batches`].
+pub struct RecordBatches {
+    entries: BTreeTableEntries,
+    schema: SchemaRef,
+    row_id_column: Option<usize>,
+    defaults: Vec<SerialValue>,
+    batch_size: usize,
+}
+
+/// Collects the values of one column.
+enum ColumnBuilder {
+    Integer(Int64Builder),
+    Real(Float64Builder),
+    Text(StringBuilder),
+    Blob(BinaryBuilder),
+}
+
+impl<T: WithRowId> TableHandle<T> {
+    /// Reads the table into Arrow record batches of up to `batch_size` rows.
+    /// Each column's Arrow type comes from its affinity: `INTEGER` columns
+    /// become `Int64`, `REAL` and `NUMERIC` columns become `Float64`, `TEXT`
+    /// columns become `Utf8`, and the rest become `Binary`. Reading fails on a
+    /// value that does not fit its column's type, such as text stored in an
+    /// integer column.
+    pub fn to_record_batches(&self, batch_size: usize) -> Result<RecordBatches> {
+        if batch_size == 0 {
+            return Err(anyhow!("batch size must be at least 1"));
+        }
+
+        let (_, parsed) = self.db.find_table(T::NAME)?;
+        let fields = parsed
+            .columns
+            .iter()
+            .map(|column| {
+                let data_type = match column.affinity() {
+                    Affinity::Integer => DataType::Int64,
+                    Affinity::Real | Affinity::Numeric => DataType::Float64,
+                    Affinity::Text => DataType::Utf8,
+                    Affinity::Blob => DataType::Binary,
+                };
+                Field::new(&column.name, data_type, true)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(RecordBatches {
+            entries: self.rootpage()?.into_table_entries_range(None..None)?,
+            schema: Arc::new(Schema::new(fields)),
+            row_id_column: parsed.row_id_column(),
+            defaults: parsed.defaults(),
+            batch_size,
+        })
+    }
+}
+
+impl RecordBatches {
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut columns = self
+            .schema
+            .fields()
+            .iter()
+            .map(|field| ColumnBuilder::new(field.data_type(), self.batch_size))
+            .collect::<Vec<_>>();
+
+        let mut rows = 0;
+        while rows < self.batch_size {
+            let Some((row_id, data)) = self.entries.next().transpose()? else {
+                break;
+            };
+            let mut values = Record::from(data).into_values();
+            for (index, column) in columns.iter_mut().enumerate() {
+                // Columns missing from the end of the record take their
+                // DEFAULT.
+                let value = match values.next().transpose()? {
+                    Some(SerialValue::Null) | None if self.row_id_column == Some(index) => {
+                        SerialValue::from_i64(row_id)
+                    }
+                    Some(value) => value,
+                    None => self.defaults[index].clone(),
+                };
+                column.append(value).map_err(|err| {
+                    anyhow!("{err} in column {}", self.schema.field(index).name())
+                })?;
+            }
+            rows += 1;
+        }
+
+        if rows == 0 {
+            return Ok(None);
+        }
+        let arrays = columns.into_iter().map(ColumnBuilder::finish).collect();
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), arrays)?))
+    }
+}
+
+impl Iterator for RecordBatches {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType, capacity: usize) -> Self {
+        match data_type {
+            DataType::Int64 => Self::Integer(Int64Builder::with_capacity(capacity)),
+            DataType::Float64 => Self::Real(Float64Builder::with_capacity(capacity)),
+            DataType::Utf8 => Self::Text(StringBuilder::new()),
+            _ => Self::Blob(BinaryBuilder::new()),
+        }
+    }
+
+    fn append(&mut self, value: SerialValue) -> Result<()> {
+        match (self, value) {
+            (Self::Integer(builder), SerialValue::Null) => builder.append_null(),
+            (Self::Real(builder), SerialValue::Null) => builder.append_null(),
+            (Self::Text(builder), SerialValue::Null) => builder.append_null(),
+            (Self::Blob(builder), SerialValue::Null) => builder.append_null(),
+            (Self::Real(builder), SerialValue::F64(value)) => builder.append_value(value.get()),
+            (Self::Text(builder), SerialValue::Text(value)) => builder.append_value(value),
+            (Self::Blob(builder), SerialValue::Blob(value)) => builder.append_value(value),
+            (Self::Blob(builder), SerialValue::Text(value)) => builder.append_value(value),
+            (Self::Integer(builder), value) => match value.as_i64() {
+                Some(value) => builder.append_value(value),
+                None => return Err(anyhow!("{:?} is not an integer", value.serial_type())),
+            },
+            (Self::Real(builder), value) => match value.as_i64() {
+                Some(value) => builder.append_value(value as f64),
+                None => return Err(anyhow!("{:?} is not a number", value.serial_type())),
+            },
+            (_, value) => {
+                return Err(anyhow!(
+                    "{:?} does not match the column type",
+                    value.serial_type()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> ArrayRef {
+        match self {
+            Self::Integer(mut builder) => Arc::new(builder.finish()),
+            Self::Real(mut builder) => Arc::new(builder.finish()),
+            Self::Text(mut builder) => Arc::new(builder.finish()),
+            Self::Blob(mut builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::Int64Type, Array};
+    use serde::Deserialize;
+
+    use crate::{
+        physical::db::DB,
+        schema::{SchemaType, Table},
+    };
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, Table)]
+    struct Numbers {}
+
+    #[derive(Debug, Deserialize, Table)]
+    struct Tasks {}
+
+    #[test]
+    fn test_to_record_batches() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let batches = table
+            .to_record_batches(5000)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3000);
+        assert_eq!(batch.schema().field(2).data_type(), &DataType::Utf8);
+
+        let ids = batch.column(0).as_primitive::<Int64Type>();
+        let squares = batch.column(1).as_primitive::<Int64Type>();
+        let names = batch.column(2).as_string::<i32>();
+        assert_eq!(
+            (ids.value(11), squares.value(11), names.value(11)),
+            (12, 144, "number 12")
+        );
+
+        let sizes = table
+            .to_record_batches(1000)
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [1000, 1000, 1000]);
+    }
+
+    #[test]
+    fn test_record_batch_nulls() {
+        let db = DB::open("examples/altered.db").unwrap();
+        let batch = db
+            .table::<Tasks>()
+            .unwrap()
+            .to_record_batches(10)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        let done_at = batch.column(2).as_string::<i32>();
+        assert_eq!(done_at.null_count(), 2);
+        assert!(done_at.is_null(0));
+        assert_eq!(done_at.value(2), "2024-01-02");
+    }
+}
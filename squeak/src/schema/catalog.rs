@@ -0,0 +1,53 @@
+use anyhow::{anyhow, Result};
+
+use crate::physical::db::DB;
+
+use super::{Table, TableHandle};
+
+/// A set of databases under names, like those attached to a SQLite
+/// connection, so that rows can be read from several files together.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    databases: Vec<(String, DB)>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a database under the given name. Fails if the name is taken.
+    pub fn attach(&mut self, name: &str, db: DB) -> Result<()> {
+        if self.get(name).is_some() {
+            return Err(anyhow!("database {name} is already attached"));
+        }
+        self.databases.push((name.to_owned(), db));
+        Ok(())
+    }
+
+    /// Removes the database with the given name, returning it if there was
+    /// one.
+    pub fn detach(&mut self, name: &str) -> Option<DB> {
+        let position = self.databases.iter().position(|(n, _)| n == name)?;
+        Some(self.databases.remove(position).1)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DB> {
+        self.databases
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, db)| db)
+    }
+
+    /// The names of the attached databases, in the order they were attached.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.databases.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Opens a table in the database with the given name.
+    pub fn table<T: Table>(&self, database: &str) -> Result<TableHandle<T>> {
+        self.get(database)
+            .ok_or_else(|| anyhow!("no database named {database} is attached"))?
+            .table()
+    }
+}
@@ -0,0 +1,196 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use anyhow::Result;
+
+use crate::physical::db::DB;
+
+use super::{select::NamedRow, Schema, SchemaType};
+
+/// A way in which one database differs from another. See [`DB::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// A table, index, view or trigger that only the other database has.
+    Added {
+        name: String,
+    },
+    /// A table, index, view or trigger that the other database does not have.
+    Removed {
+        name: String,
+    },
+    /// A table, index, view or trigger whose SQL is not the same in both.
+    SchemaChanged {
+        name: String,
+    },
+    RowAdded {
+        table: String,
+        row_id: i64,
+    },
+    RowRemoved {
+        table: String,
+        row_id: i64,
+    },
+    RowChanged {
+        table: String,
+        row_id: i64,
+    },
+}
+
+impl DB {
+    /// Lists the changes that would turn this database into `other`. The
+    /// schemas are compared by name and SQL, then the rows of each table that
+    /// is defined the same way in both are matched by row id.
+    ///
+    /// Rows of `WITHOUT ROWID` and virtual tables are not compared.
+    pub fn diff(&self, other: &DB) -> Result<Vec<Difference>> {
+        let ours = self.schema_by_name()?;
+        let theirs = other.schema_by_name()?;
+
+        let mut differences = Vec::new();
+        let mut tables = Vec::new();
+        for (name, schema) in &ours {
+            match theirs.get(name) {
+                None => differences.push(Difference::Removed { name: name.clone() }),
+                Some(other_schema) if other_schema.sql != schema.sql => {
+                    differences.push(Difference::SchemaChanged { name: name.clone() })
+                }
+                Some(_) => {
+                    if schema.type_ == SchemaType::Table
+                        && schema.rootpage != 0
+                        && !schema.parse_create_table()?.without_row_id
+                    {
+                        tables.push(name);
+                    }
+                }
+            }
+        }
+        for name in theirs.keys() {
+            if !ours.contains_key(name) {
+                differences.push(Difference::Added { name: name.clone() });
+            }
+        }
+
+        for table in tables {
+            diff_rows(
+                table,
+                self.select(table)?,
+                other.select(table)?,
+                &mut differences,
+            )?;
+        }
+        Ok(differences)
+    }
+
+    fn schema_by_name(&self) -> Result<BTreeMap<String, Schema>> {
+        self.table::<Schema>()?
+            .iter()?
+            .map(|schema| schema.map(|schema| (schema.name.clone(), schema)))
+            .collect()
+    }
+}
+
+/// Walks both tables in row id order, like a merge join that also reports the
+/// rows that have no match.
+fn diff_rows(
+    table: &str,
+    mut ours: impl Iterator<Item = Result<NamedRow>>,
+    mut theirs: impl Iterator<Item = Result<NamedRow>>,
+    differences: &mut Vec<Difference>,
+) -> Result<()> {
+    let mut our_row = ours.next().transpose()?;
+    let mut their_row = theirs.next().transpose()?;
+    loop {
+        let (ordering, row_id) = match (&our_row, &their_row) {
+            (None, None) => return Ok(()),
+            (Some(ours), None) => (Ordering::Less, ours.row_id()),
+            (None, Some(theirs)) => (Ordering::Greater, theirs.row_id()),
+            (Some(ours), Some(theirs)) => match ours.row_id().cmp(&theirs.row_id()) {
+                Ordering::Greater => (Ordering::Greater, theirs.row_id()),
+                ordering => (ordering, ours.row_id()),
+            },
+        };
+
+        let table = table.to_owned();
+        match ordering {
+            Ordering::Less => {
+                differences.push(Difference::RowRemoved { table, row_id });
+                our_row = ours.next().transpose()?;
+            }
+            Ordering::Greater => {
+                differences.push(Difference::RowAdded { table, row_id });
+                their_row = theirs.next().transpose()?;
+            }
+            Ordering::Equal => {
+                if our_row.as_ref().map(|row| row.values())
+                    != their_row.as_ref().map(|row| row.values())
+                {
+                    differences.push(Difference::RowChanged { table, row_id });
+                }
+                our_row = ours.next().transpose()?;
+                their_row = theirs.next().transpose()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let db = DB::open("examples/join.db").unwrap();
+        assert_eq!(db.diff(&db.clone()).unwrap(), []);
+
+        // The same database, with the total of order 4 changed.
+        let changed = DB::open("examples/join_changed.db").unwrap();
+        assert_eq!(
+            db.diff(&changed).unwrap(),
+            [Difference::RowChanged {
+                table: "orders".to_owned(),
+                row_id: 4
+            }]
+        );
+
+        let empty = DB::open("examples/empty.db").unwrap();
+        assert_eq!(
+            db.diff(&empty).unwrap(),
+            [
+                Difference::Removed {
+                    name: "customers".to_owned()
+                },
+                Difference::Removed {
+                    name: "orders".to_owned()
+                },
+                Difference::Added {
+                    name: "empty".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_rows() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let without = |row_id| {
+            db.select("numbers")
+                .unwrap()
+                .filter(move |row| row.as_ref().map_or(true, |row| row.row_id() != row_id))
+        };
+
+        let mut differences = Vec::new();
+        diff_rows("numbers", without(5), without(7), &mut differences).unwrap();
+        assert_eq!(
+            differences,
+            [
+                Difference::RowAdded {
+                    table: "numbers".to_owned(),
+                    row_id: 5
+                },
+                Difference::RowRemoved {
+                    table: "numbers".to_owned(),
+                    row_id: 7
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use anyhow::Result;
+
+use super::{TableHandle, WithoutRowId};
+
+const BITS_PER_KEY: usize = 10;
+const HASH_COUNT: u64 = 7;
+
+/// A Bloom filter over the keys of an index. It never reports a key that is
+/// present as missing, but may report a missing key as present.
+#[derive(Debug, Clone)]
+pub struct KeyFilter<K> {
+    bits: Vec<u64>,
+    _marker: PhantomData<fn(&K)>,
+}
+
+impl<K: Hash> KeyFilter<K> {
+    fn with_capacity(keys: usize) -> Self {
+        let words = (keys * BITS_PER_KEY).div_ceil(64).max(1);
+        Self {
+            bits: vec![0; words],
+            _marker: PhantomData,
+        }
+    }
+
+    fn bit_indices(&self, key: &K) -> impl Iterator<Item = usize> {
+        let hash = |seed: u8| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash(0), hash(1));
+        let bit_count = self.bits.len() as u64 * 64;
+
+        (0..HASH_COUNT).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+    }
+
+    fn insert(&mut self, key: &K) {
+        for bit in self.bit_indices(key).collect::<Vec<_>>() {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns `false` if the key is definitely not in the index.
+    pub fn might_contain(&self, key: &K) -> bool {
+        self.bit_indices(key)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+impl<I: WithoutRowId> TableHandle<I>
+where
+    I::SortedFields: Hash,
+{
+    /// Scans the index once to build a filter over its keys.
+    pub fn build_key_filter(&self) -> Result<KeyFilter<I::SortedFields>> {
+        let mut filter = KeyFilter::with_capacity(self.row_count()? as usize);
+        for row in self.iter_without_row_id()? {
+            filter.insert(&row?.into_sorted_fields());
+        }
+        Ok(filter)
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+
+/// Pairs the rows of two iterators that are both sorted by a join key. See
+/// [`merge_join`].
+pub struct MergeJoin<L, R, LI, RI, LF, RF, K>
+where
+    RI: Iterator<Item = Result<R>>,
+{
+    left: LI,
+    right: RI,
+    left_key: LF,
+    right_key: RF,
+    // The next right row, which has not been matched yet
+    right_peek: Option<(K, R)>,
+    // The right rows that share the key of the last matched left row
+    group: Vec<R>,
+    group_key: Option<K>,
+    // The left row being paired with the group, and the next index to pair
+    current: Option<(L, usize)>,
+}
+
+/// Joins two iterators that are sorted in ascending order of their join keys,
+/// such as two tables iterated in row id order where the key grows with the
+/// row id. Yields a pair for every left and right row with equal keys,
+/// advancing whichever side is behind.
+///
+/// Rows that are out of order are not detected, and may cause matches to be
+/// missed.
+pub fn merge_join<L, R, LI, RI, LF, RF, K>(
+    left: LI,
+    right: RI,
+    left_key: LF,
+    right_key: RF,
+) -> MergeJoin<L, R, LI::IntoIter, RI::IntoIter, LF, RF, K>
+where
+    LI: IntoIterator<Item = Result<L>>,
+    RI: IntoIterator<Item = Result<R>>,
+    LF: FnMut(&L) -> K,
+    RF: FnMut(&R) -> K,
+    K: Ord,
+{
+    MergeJoin {
+        left: left.into_iter(),
+        right: right.into_iter(),
+        left_key,
+        right_key,
+        right_peek: None,
+        group: Vec::new(),
+        group_key: None,
+        current: None,
+    }
+}
+
+impl<L, R, LI, RI, LF, RF, K> MergeJoin<L, R, LI, RI, LF, RF, K>
+where
+    RI: Iterator<Item = Result<R>>,
+    RF: FnMut(&R) -> K,
+{
+    fn peek_right(&mut self) -> Result<Option<&(K, R)>> {
+        if self.right_peek.is_none() {
+            if let Some(row) = self.right.next().transpose()? {
+                self.right_peek = Some(((self.right_key)(&row), row));
+            }
+        }
+        Ok(self.right_peek.as_ref())
+    }
+}
+
+impl<L, R, LI, RI, LF, RF, K> MergeJoin<L, R, LI, RI, LF, RF, K>
+where
+    L: Clone,
+    R: Clone,
+    LI: Iterator<Item = Result<L>>,
+    RI: Iterator<Item = Result<R>>,
+    LF: FnMut(&L) -> K,
+    RF: FnMut(&R) -> K,
+    K: Ord,
+{
+    fn next_pair(&mut self) -> Result<Option<(L, R)>> {
+        loop {
+            if let Some((left, index)) = &mut self.current {
+                if let Some(right) = self.group.get(*index) {
+                    *index += 1;
+                    return Ok(Some((left.clone(), right.clone())));
+                }
+                self.current = None;
+            }
+
+            let Some(left) = self.left.next().transpose()? else {
+                return Ok(None);
+            };
+            let key = (self.left_key)(&left);
+            if self.group_key.as_ref() == Some(&key) {
+                self.current = Some((left, 0));
+                continue;
+            }
+
+            // Skip the right rows that are behind this left row.
+            loop {
+                match self.peek_right()? {
+                    Some((right_key, _)) if *right_key < key => self.right_peek = None,
+                    Some(_) => break,
+                    // Later left rows cannot match either.
+                    None => return Ok(None),
+                }
+            }
+
+            self.group.clear();
+            while let Some((right_key, _)) = self.peek_right()? {
+                if *right_key != key {
+                    break;
+                }
+                let (_, right) = self.right_peek.take().unwrap();
+                self.group.push(right);
+            }
+            self.group_key = Some(key);
+            self.current = Some((left, 0));
+        }
+    }
+}
+
+impl<L, R, LI, RI, LF, RF, K> Iterator for MergeJoin<L, R, LI, RI, LF, RF, K>
+where
+    L: Clone,
+    R: Clone,
+    LI: Iterator<Item = Result<L>>,
+    RI: Iterator<Item = Result<R>>,
+    LF: FnMut(&L) -> K,
+    RF: FnMut(&R) -> K,
+    K: Ord,
+{
+    type Item = Result<(L, R)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_pair().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{
+        physical::db::DB,
+        schema::{SchemaType, Table, WithRowId},
+    };
+
+    use super::*;
+
+    #[derive(Debug, Clone, Deserialize, Table)]
+    #[table(name = "customers")]
+    struct Customer {
+        #[table(row_id)]
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Table)]
+    #[table(name = "orders")]
+    struct Order {
+        #[table(row_id)]
+        id: i64,
+        customer_id: i64,
+        total: i64,
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let db = DB::open("examples/join.db").unwrap();
+        let customers = db.table::<Customer>().unwrap();
+        let orders = db.table::<Order>().unwrap();
+
+        let pairs = merge_join(
+            customers.iter().unwrap(),
+            orders.iter().unwrap(),
+            |customer| customer.id,
+            |order| order.customer_id,
+        )
+        .map(|pair| {
+            let (customer, order) = pair.unwrap();
+            (customer.name, order.id, order.total)
+        })
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            pairs,
+            [
+                ("ann".to_owned(), 1, 10),
+                ("ann".to_owned(), 2, 11),
+                ("cat".to_owned(), 3, 30),
+                ("eve".to_owned(), 5, 50),
+                ("eve".to_owned(), 6, 51),
+                ("eve".to_owned(), 7, 52),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_join_duplicate_keys() {
+        let left = [1, 2, 2, 4].map(Ok);
+        let right = [(2, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].map(Ok);
+        let pairs = merge_join(left, right, |&left| left, |&(key, _)| key)
+            .map(|pair| pair.map(|(left, (_, right))| (left, right)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(pairs, [(2, 'a'), (2, 'b'), (2, 'a'), (2, 'b'), (4, 'd')]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{cmp::Ordering, fmt, marker::PhantomData, sync::Arc};
 
 use anyhow::{anyhow, Result};
 use serde::{
@@ -7,13 +7,31 @@ use serde::{
 };
 use squeak_macros::Table;
 
-use crate::physical::{btree::BTreePage, buf::ArcBufSlice, db::DB};
+use crate::physical::{
+    btree::{cursor::Direction, BTreePage},
+    buf::ArcBufSlice,
+    db::DB,
+};
+
+pub use crate::physical::btree::cursor::Cursor;
 
-use self::record::Record;
+use self::{
+    record::{Record, SerialValue},
+    sql::ParsedTable,
+};
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod catalog;
+pub mod diff;
+pub mod filter;
+pub mod join;
+pub mod predicate;
 pub mod range;
 pub mod record;
+pub mod select;
 pub mod serialization;
+pub mod sql;
 
 #[derive(Debug, Clone, Deserialize, Table)]
 #[table(name = "sqlite_schema")]
@@ -26,6 +44,13 @@ pub struct Schema {
     pub sql: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSummary {
+    pub name: String,
+    pub rootpage: u32,
+    pub row_count: u64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SchemaType {
@@ -38,39 +63,159 @@ pub enum SchemaType {
 pub trait Table: DeserializeOwned {
     const TYPE: SchemaType;
     const NAME: &'static str;
+    /// The number of columns the type expects, if known. Used to explain
+    /// records that fail to deserialize.
+    const COLUMN_COUNT: Option<usize> = None;
+}
+
+/// A record, or the table it was read from, had a different number of columns
+/// than the type it was read as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnCountMismatch {
+    pub expected: usize,
+    pub found: usize,
+    pub table: &'static str,
+}
+
+impl fmt::Display for ColumnCountMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} columns in table {} but the record has {}",
+            self.expected, self.table, self.found
+        )
+    }
 }
 
+impl std::error::Error for ColumnCountMismatch {}
+
 pub trait WithRowId: Table {
-    fn deserialize_row_id(&mut self, _row_id: u64) {}
+    /// The column that is an alias for the row id, if any. SQLite stores NULL
+    /// in that column, so the row id is read in its place.
+    const ROW_ID_COLUMN: Option<usize> = None;
+
+    fn deserialize_row_id(&mut self, _row_id: i64) {}
 }
 
 pub trait WithoutRowId: Table {
+    /// The key that entries are sorted by. Columns stored in descending
+    /// order, such as `x DESC` in `CREATE INDEX`, should be wrapped in
+    /// [`std::cmp::Reverse`] so that they compare the way they are stored.
     type SortedFields: Ord;
 
     fn into_sorted_fields(self) -> Self::SortedFields;
 }
 
 pub trait Index<T: Table>: WithoutRowId {
-    fn get_row_id(&self) -> u64;
+    fn get_row_id(&self) -> i64;
+}
+
+impl Schema {
+    /// Parses the `CREATE TABLE` statement of a table.
+    pub fn parse_create_table(&self) -> Result<ParsedTable> {
+        let sql = self
+            .sql
+            .as_deref()
+            .filter(|_| self.type_ == SchemaType::Table)
+            .ok_or_else(|| anyhow!("{} is not a table with a CREATE TABLE statement", self.name))?;
+        sql::parse_create_table(sql)
+    }
+
+    /// Returns the column names declared in the `CREATE TABLE` statement.
+    pub fn column_names(&self) -> Result<Vec<String>> {
+        let table = self.parse_create_table()?;
+        Ok(table
+            .columns
+            .into_iter()
+            .map(|column| column.name)
+            .collect())
+    }
 }
 
-fn deserialize_record_with_row_id<T: WithRowId>((row_id, buf): (u64, ArcBufSlice)) -> Result<T> {
+fn deserialize_record_with_row_id<T: WithRowId>(
+    layout: &TableLayout,
+    (row_id, buf): (i64, ArcBufSlice),
+) -> Result<T> {
+    layout.check_column_count::<T>()?;
     let record = Record::from(buf);
-    let mut value = T::deserialize(record.into_deserializer())?;
+    let mut deserializer = record
+        .clone()
+        .into_deserializer()
+        .with_defaults(layout.defaults.clone());
+    if let Some(column) = T::ROW_ID_COLUMN.or(layout.row_id_column) {
+        deserializer = deserializer.with_row_id(column, row_id);
+    }
+    let mut value = T::deserialize(deserializer).map_err(|err| explain_error::<T>(&record, err))?;
     value.deserialize_row_id(row_id);
     Ok(value)
 }
 
-fn deserialize_record<T: DeserializeOwned>(buf: ArcBufSlice) -> Result<T> {
+fn deserialize_record<T: Table>(buf: ArcBufSlice) -> Result<T> {
     let record = Record::from(buf);
-    let value = T::deserialize(record.into_deserializer())?;
+    let value = T::deserialize(record.clone().into_deserializer())
+        .map_err(|err| explain_error::<T>(&record, err))?;
     Ok(value)
 }
 
+/// Replaces a deserialization error with [`ColumnCountMismatch`] when the
+/// record has more columns than the type, which is the likely cause. Records
+/// with fewer columns may have been written before `ALTER TABLE ADD COLUMN`,
+/// so the error from the missing values is kept.
+fn explain_error<T: Table>(record: &Record, err: impl Into<anyhow::Error>) -> anyhow::Error {
+    let found = record.types().count();
+    match T::COLUMN_COUNT {
+        Some(expected) if expected < found => ColumnCountMismatch {
+            expected,
+            found,
+            table: T::NAME,
+        }
+        .into(),
+        _ => err.into(),
+    }
+}
+
+/// How the rows of a table are stored, according to its SQL.
+#[derive(Debug, Clone, Default)]
+struct TableLayout {
+    // The `INTEGER PRIMARY KEY` column, for types that do not mark it with
+    // `#[table(row_id)]`
+    row_id_column: Option<usize>,
+    // The number of columns declared in the SQL
+    column_count: Option<usize>,
+    // The DEFAULT of each column, for rows written before it was added
+    defaults: Arc<[SerialValue]>,
+}
+
+impl TableLayout {
+    fn new(parsed: &ParsedTable) -> Self {
+        Self {
+            row_id_column: parsed.row_id_column(),
+            column_count: Some(parsed.columns.len()),
+            defaults: parsed.defaults().into(),
+        }
+    }
+
+    /// Reports a type with a different number of fields than the table has
+    /// columns. Otherwise, the missing columns of a short record would be
+    /// padded past the end of the table.
+    fn check_column_count<T: Table>(&self) -> Result<()> {
+        match (T::COLUMN_COUNT, self.column_count) {
+            (Some(expected), Some(found)) if expected != found => Err(ColumnCountMismatch {
+                expected,
+                found,
+                table: T::NAME,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TableHandle<T> {
     db: DB,
     rootpage: u32,
+    layout: TableLayout,
     _marker: PhantomData<T>,
 }
 
@@ -79,6 +224,7 @@ impl<T> Clone for TableHandle<T> {
         Self {
             db: self.db.clone(),
             rootpage: self.rootpage,
+            layout: self.layout.clone(),
             _marker: PhantomData,
         }
     }
@@ -99,40 +245,208 @@ impl<T: Table> TableHandle<T> {
         Ok(row)
     }
 
+    /// Creates a cursor over the rows of the table.
+    pub fn cursor(&self) -> Result<Cursor>
+    where
+        T: WithRowId,
+    {
+        Ok(Cursor::new(self.rootpage()?))
+    }
+
+    /// The smallest row id in the table, found by descending the left edge of
+    /// its b-tree rather than scanning it. `None` if the table is empty.
+    pub fn min_row_id(&self) -> Result<Option<i64>>
+    where
+        T: WithRowId,
+    {
+        self.end_row_id(Direction::Forward)
+    }
+
+    /// The largest row id in the table. See [`TableHandle::min_row_id`].
+    pub fn max_row_id(&self) -> Result<Option<i64>>
+    where
+        T: WithRowId,
+    {
+        self.end_row_id(Direction::Backward)
+    }
+
+    fn end_row_id(&self, direction: Direction) -> Result<Option<i64>> {
+        let leaf = self.rootpage()?.end_leaf(direction)?;
+        end_cell_index(&leaf, direction)
+            .map(|index| Ok(leaf.leaf_table_cell(index)?.0))
+            .transpose()
+    }
+
+    /// The smallest entry of an index or `WITHOUT ROWID` table, found by
+    /// descending the left edge of its b-tree. `None` if it is empty.
+    pub fn min_key(&self) -> Result<Option<T>>
+    where
+        T: WithoutRowId,
+    {
+        self.end_key(Direction::Forward)
+    }
+
+    /// The largest entry of an index or `WITHOUT ROWID` table. See
+    /// [`TableHandle::min_key`].
+    pub fn max_key(&self) -> Result<Option<T>>
+    where
+        T: WithoutRowId,
+    {
+        self.end_key(Direction::Backward)
+    }
+
+    fn end_key(&self, direction: Direction) -> Result<Option<T>> {
+        self.layout.check_column_count::<T>()?;
+        let leaf = self.rootpage()?.end_leaf(direction)?;
+        end_cell_index(&leaf, direction)
+            .map(|index| deserialize_record(leaf.leaf_index_cell(index)?))
+            .transpose()
+    }
+
+    /// Counts the rows in the table by walking its b-tree, without
+    /// deserializing them.
+    pub fn row_count(&self) -> Result<u64> {
+        self.rootpage()?.count_entries()
+    }
+
     pub(crate) fn rootpage(&self) -> Result<BTreePage> {
         self.db.btree_page(self.rootpage)
     }
 }
 
+fn end_cell_index(leaf: &BTreePage, direction: Direction) -> Option<u16> {
+    match (leaf.cell_count(), direction) {
+        (0, _) => None,
+        (_, Direction::Forward) => Some(0),
+        (cell_count, Direction::Backward) => Some(cell_count - 1),
+    }
+}
+
 impl DB {
     pub fn table<T: Table>(&self) -> Result<TableHandle<T>> {
-        let rootpage = if T::NAME == Schema::NAME {
-            1
+        let (rootpage, layout) = if T::NAME == Schema::NAME {
+            (1, TableLayout::default())
         } else {
-            let mut rootpage = None;
+            let mut found = None;
             for schema in self.table::<Schema>()?.iter()? {
                 let schema = schema?;
                 if schema.type_ == T::TYPE && schema.name == T::NAME {
-                    rootpage = Some(schema.rootpage);
+                    // SQL that cannot be parsed only means that the row id and
+                    // defaults are not filled in, so the table can still be
+                    // read.
+                    let layout = match schema.type_ {
+                        SchemaType::Table => schema
+                            .parse_create_table()
+                            .map(|parsed| TableLayout::new(&parsed))
+                            .unwrap_or_default(),
+                        _ => TableLayout::default(),
+                    };
+                    found = Some((schema.rootpage, layout));
                     break;
                 }
             }
-            rootpage.ok_or_else(|| anyhow!("Table {} not found in schema", T::NAME))?
+            found.ok_or_else(|| anyhow!("Table {} not found in schema", T::NAME))?
         };
 
         Ok(TableHandle {
             db: self.clone(),
             rootpage,
+            layout,
             _marker: PhantomData,
         })
     }
+
+    /// Finds the rows with entries matching `key1` in one index and `key2` in
+    /// another, by intersecting the row ids found in each.
+    pub fn intersect_indexes<T, I1, I2>(
+        &self,
+        key1: &I1::SortedFields,
+        key2: &I2::SortedFields,
+    ) -> Result<Vec<T>>
+    where
+        T: WithRowId,
+        I1: Index<T>,
+        I2: Index<T>,
+    {
+        let mut row_ids1 = self
+            .table::<I1>()?
+            .get_all(key1)?
+            .map(|entry| entry.map(|entry| entry.get_row_id()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut row_ids2 = self
+            .table::<I2>()?
+            .get_all(key2)?
+            .map(|entry| entry.map(|entry| entry.get_row_id()))
+            .collect::<Result<Vec<_>>>()?;
+        row_ids1.sort_unstable();
+        row_ids2.sort_unstable();
+
+        let mut row_ids = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < row_ids1.len() && j < row_ids2.len() {
+            match row_ids1[i].cmp(&row_ids2[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    row_ids.push(row_ids1[i]);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+
+        self.table::<T>()?
+            .get_many(row_ids)?
+            .into_iter()
+            .map(|row| row.ok_or_else(|| anyhow!("Index entry refers to a missing row")))
+            .collect()
+    }
+
+    /// Lists the tables in the schema with their row counts, excluding
+    /// SQLite's internal tables and virtual tables, which have no b-tree of
+    /// their own.
+    pub fn tables(&self) -> Result<Vec<TableSummary>> {
+        let mut tables = Vec::new();
+        for schema in self.table::<Schema>()?.iter()? {
+            let schema = schema?;
+            if schema.type_ == SchemaType::Table
+                && schema.rootpage != 0
+                && !schema.name.starts_with("sqlite_")
+            {
+                let row_count = self.btree_page(schema.rootpage)?.count_entries()?;
+                tables.push(TableSummary {
+                    name: schema.name,
+                    rootpage: schema.rootpage,
+                    row_count,
+                });
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Lists the triggers in the schema as `(name, table, sql)`.
+    pub fn triggers(&self) -> Result<Vec<(String, String, String)>> {
+        let mut triggers = Vec::new();
+        for schema in self.table::<Schema>()?.iter()? {
+            let schema = schema?;
+            if schema.type_ == SchemaType::Trigger {
+                let sql = schema
+                    .sql
+                    .ok_or_else(|| anyhow!("Trigger {} has no SQL", schema.name))?;
+                triggers.push((schema.name, schema.tbl_name, sql));
+            }
+        }
+        Ok(triggers)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::sync::{Arc, Mutex};
 
-    use crate::physical::db::DB;
+    use super::{record::SerialValue, serialization::row_id, *};
+
+    use crate::physical::{btree::BTreePageType, db::DB};
 
     #[derive(Debug, Clone, Deserialize, Table)]
     struct Empty {}
@@ -143,6 +457,14 @@ mod tests {
         pub string: String,
     }
 
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    #[table(without_rowid)]
+    struct KeyValue {
+        #[table(primary_key)]
+        key: String,
+        value: i64,
+    }
+
     #[test]
     fn test_read_schema() {
         let db = DB::open("examples/empty.db").unwrap();
@@ -167,6 +489,349 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_full_range() {
+        let db = DB::open("examples/string_index.db").unwrap();
+        let table = db.table::<Schema>().unwrap();
+
+        let names = |rows: Vec<Schema>| rows.into_iter().map(|row| row.name).collect::<Vec<_>>();
+        let all = table.get(..).unwrap().collect::<Result<Vec<_>>>().unwrap();
+        let iterated = table.iter().unwrap().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(names(all), names(iterated));
+    }
+
+    #[test]
+    fn test_column_names() {
+        let db = DB::open("examples/empty.db").unwrap();
+        let schema = db.table::<Schema>().unwrap().get(1).unwrap().unwrap();
+        assert_eq!(schema.column_names().unwrap(), vec!["id"]);
+
+        let schema = Schema {
+            sql: Some(
+                "CREATE TABLE t (\"a b\" TEXT, c NUMERIC(10, 2), PRIMARY KEY (c))".to_owned(),
+            ),
+            ..schema
+        };
+        assert_eq!(schema.column_names().unwrap(), vec!["a b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_create_table() {
+        let db = DB::open("examples/empty.db").unwrap();
+        let schema = db.table::<Schema>().unwrap().get(1).unwrap().unwrap();
+
+        let table = schema.parse_create_table().unwrap();
+        assert_eq!(table.name, "empty");
+        assert_eq!(
+            table.columns,
+            vec![sql::ParsedColumn {
+                name: "id".to_owned(),
+                declared_type: Some("integer".to_owned()),
+                not_null: true,
+                primary_key: true,
+                default: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_map() {
+        let db = DB::open("examples/empty.db").unwrap();
+        let (_, record) = db.btree_page(1).unwrap().leaf_table_cell(0).unwrap();
+
+        let columns = ["type", "name", "tbl_name", "rootpage", "sql"];
+        let row =
+            serde_json::Map::deserialize(Record::from(record).into_map_deserializer(&columns))
+                .unwrap();
+        assert_eq!(
+            serde_json::Value::Object(row),
+            serde_json::json!({
+                "type": "table",
+                "name": "empty",
+                "tbl_name": "empty",
+                "rootpage": 2,
+                "sql": "CREATE TABLE empty (id integer not null primary key)",
+            })
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    struct Users {
+        #[table(primary_key)]
+        name: String,
+        #[table(unique)]
+        email: String,
+        #[table(unique)]
+        nickname: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    struct Signed {
+        #[table(row_id)]
+        #[serde(with = "row_id")]
+        id: i64,
+        label: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    struct Numbers {
+        #[table(row_id)]
+        #[serde(with = "row_id")]
+        id: i64,
+        square: i64,
+        name: String,
+    }
+
+    #[test]
+    fn test_negative_row_ids() {
+        let db = DB::open("examples/signed.db").unwrap();
+        let table = db.table::<Signed>().unwrap();
+
+        let row = table.get(-3).unwrap().unwrap();
+        assert_eq!(row.id, -3);
+        assert_eq!(row.label, "minus three");
+        assert_eq!(table.get(i64::MIN).unwrap().unwrap().label, "min");
+        assert_eq!(table.get(-4).unwrap(), None);
+
+        let ids = table
+            .iter()
+            .unwrap()
+            .map(|row| row.map(|row| row.id))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(ids, vec![i64::MIN, -3, -2, -1, 0, 1, 2, 3, i64::MAX]);
+    }
+
+    #[test]
+    fn test_signed_row_id_ranges() {
+        let db = DB::open("examples/signed.db").unwrap();
+        let table = db.table::<Signed>().unwrap();
+
+        fn ids(rows: impl Iterator<Item = Result<Signed>>) -> Vec<i64> {
+            rows.map(|row| row.map(|row| row.id))
+                .collect::<Result<Vec<_>>>()
+                .unwrap()
+        }
+        assert_eq!(ids(table.get(-2..=2).unwrap()), vec![-2, -1, 0, 1, 2]);
+        assert_eq!(ids(table.get(-1..1).unwrap()), vec![-1, 0]);
+        assert_eq!(ids(table.get(..-2).unwrap()), vec![i64::MIN, -3]);
+        assert_eq!(ids(table.get(3..=i64::MAX).unwrap()), vec![3, i64::MAX]);
+        assert_eq!(ids(table.get(..=i64::MAX).unwrap()).len(), 9);
+        assert_eq!(table.get(i64::MAX).unwrap().unwrap().label, "max");
+    }
+
+    #[test]
+    fn test_read_multi_level_table() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let ids = table
+            .iter()
+            .unwrap()
+            .map(|row| row.map(|row| row.id))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(ids, (1..=3000).collect::<Vec<_>>());
+
+        for id in [1, 2, 500, 1234, 2999, 3000] {
+            let row = table.get(id).unwrap().unwrap();
+            assert_eq!((row.id, row.square), (id, id * id));
+            assert_eq!(row.name, format!("number {id}"));
+        }
+        assert_eq!(table.get(0).unwrap(), None);
+        assert_eq!(table.get(3001).unwrap(), None);
+
+        let range = table
+            .get(1000..1100)
+            .unwrap()
+            .map(|row| row.map(|row| row.id))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(range, (1000..1100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_cursor() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+        let mut cursor = table.cursor().unwrap();
+        assert!(cursor.current().unwrap().is_none());
+
+        fn current_id(cursor: &Cursor) -> i64 {
+            cursor.current().unwrap().unwrap().0
+        }
+
+        assert!(cursor.seek(1500).unwrap());
+        let (id, record) = cursor.current().unwrap().unwrap();
+        assert_eq!(id, 1500);
+        assert_eq!(
+            record.project(&[1]).unwrap(),
+            [SerialValue::from_i64(1500 * 1500)]
+        );
+
+        // Step across several leaf pages in both directions.
+        for expected in 1501..1700 {
+            assert!(cursor.next().unwrap());
+            assert_eq!(current_id(&cursor), expected);
+        }
+        for expected in (1300..1699).rev() {
+            assert!(cursor.prev().unwrap());
+            assert_eq!(current_id(&cursor), expected);
+        }
+
+        assert!(cursor.first().unwrap());
+        assert_eq!(current_id(&cursor), 1);
+        assert!(!cursor.prev().unwrap());
+        assert!(cursor.current().unwrap().is_none());
+
+        assert!(cursor.last().unwrap());
+        assert_eq!(current_id(&cursor), 3000);
+        assert!(!cursor.next().unwrap());
+
+        assert!(!cursor.seek(3001).unwrap());
+        assert!(cursor.seek(i64::MIN).unwrap());
+        assert_eq!(current_id(&cursor), 1);
+    }
+
+    #[test]
+    fn test_cursor_forward_then_backward() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+        let mut cursor = table.cursor().unwrap();
+
+        let mut forward = Vec::new();
+        let mut more = cursor.first().unwrap();
+        while more {
+            let (id, record) = cursor.current().unwrap().unwrap();
+            forward.push((id, record.project(&[1, 2]).unwrap()));
+
+            // Stepping forward and straight back must land on the same cell.
+            if cursor.next().unwrap() {
+                assert!(cursor.prev().unwrap());
+                let (back_id, back_record) = cursor.current().unwrap().unwrap();
+                assert_eq!(back_id, id);
+                assert_eq!(
+                    back_record.project(&[1, 2]).unwrap(),
+                    forward.last().unwrap().1
+                );
+                assert!(cursor.next().unwrap());
+            } else {
+                more = false;
+            }
+        }
+
+        let mut backward = Vec::new();
+        let mut more = cursor.last().unwrap();
+        while more {
+            let (id, record) = cursor.current().unwrap().unwrap();
+            backward.push((id, record.project(&[1, 2]).unwrap()));
+            more = cursor.prev().unwrap();
+        }
+        backward.reverse();
+
+        assert_eq!(forward.len(), 3000);
+        assert_eq!(forward, backward);
+        let iter_ids = table
+            .iter()
+            .unwrap()
+            .map(|row| row.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            forward.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            iter_ids
+        );
+    }
+
+    #[test]
+    fn test_cursor_on_index_btree() {
+        // Declared without `without_rowid`, so it is read by row id.
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "key_value")]
+        #[allow(dead_code)]
+        struct KeyValue {
+            key: String,
+            value: i64,
+        }
+
+        let db = DB::open("examples/without_rowid.db").unwrap();
+        let table = db.table::<KeyValue>().unwrap();
+        let err = table.cursor().unwrap().first().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a table b-tree page, found LeafIndex"
+        );
+        assert!(table.iter().unwrap().next().unwrap().is_err());
+        assert!(table.cursor().unwrap().seek(1).is_err());
+    }
+
+    #[test]
+    fn test_take_while_id() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let pages = Arc::new(Mutex::new(Vec::new()));
+        let hook_pages = pages.clone();
+        db.on_page_access(move |page_number, _| {
+            hook_pages.lock().unwrap().push(page_number);
+        });
+
+        let ids = table
+            .take_while_id(|id| id < 5 || id % 2 == 0)
+            .unwrap()
+            .map(|row| row.map(|row| row.id))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(ids, [1, 2, 3, 4]);
+
+        // Only the path to the first leaf is read.
+        let mut pages = pages.lock().unwrap().clone();
+        pages.dedup();
+        assert_eq!(pages.len(), 3);
+    }
+
+    #[test]
+    fn test_get_many() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let rows = table.get_many([5, 1, 9]).unwrap();
+        let expected = [5, 1, 9].map(|id| table.get(id).unwrap());
+        assert_eq!(rows, expected);
+
+        let ids = [3000, 0, 2999, 17, 17, 1500, 3001, 1, 640];
+        let rows = table.get_many(ids).unwrap();
+        for (id, row) in ids.into_iter().zip(rows) {
+            assert_eq!(row, table.get(id).unwrap(), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_buffer_owned_records() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let records = table
+            .rootpage()
+            .unwrap()
+            .into_table_entries_range(Some(10)..Some(13))
+            .unwrap()
+            .map(|entry| Record::from(entry?.1).to_owned_record())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        drop(table);
+        drop(db);
+
+        let names = records
+            .iter()
+            .map(|record| match &record.values()[2] {
+                SerialValue::Text(name) => name.as_str(),
+                value => panic!("unexpected value {value:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["number 10", "number 11", "number 12"]);
+    }
+
     #[test]
     fn test_read_table() {
         let db = DB::open("examples/empty.db").unwrap();
@@ -223,6 +888,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scan_covering() {
+        let db = DB::open("examples/string_index.db").unwrap();
+        let index = db.table::<StringsPK>().unwrap();
+
+        // Pages that belong to the strings table itself are never read.
+        let table_root = db.table::<Strings>().unwrap().rootpage;
+        let touched = Arc::new(Mutex::new(Vec::new()));
+        let log = touched.clone();
+        db.on_page_access(move |page_number, _| log.lock().unwrap().push(page_number));
+
+        let strings = index
+            .scan_covering::<(String,), _>(&("baz".to_owned(),)..)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(strings, [("baz".to_owned(),), ("foo".to_owned(),)]);
+        let pages = touched.lock().unwrap().clone();
+        assert!(!pages.is_empty() && !pages.contains(&table_root));
+
+        let entries = index
+            .scan_covering::<(String, i64), _>(..=&("bar".to_owned(),))
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries, [("bar".to_owned(), 2)]);
+    }
+
+    #[test]
+    fn test_get_by_key() {
+        let db = DB::open("examples/without_rowid.db").unwrap();
+
+        assert_eq!(KeyValue::NAME, "key_value");
+
+        let table = db.table::<KeyValue>().unwrap();
+        let row = table.get_by_key(&("three".to_owned(),)).unwrap();
+        assert_eq!(
+            row,
+            Some(KeyValue {
+                key: "three".to_owned(),
+                value: 3,
+            })
+        );
+
+        let rows = table
+            .iter_without_row_id()
+            .unwrap()
+            .map(|row| row.map(|row| row.value))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows, vec![4, 1, 3, 2]);
+    }
+
+    #[test]
+    fn test_key_filter() {
+        let db = DB::open("examples/string_index.db").unwrap();
+
+        let index = db.table::<StringsPK>().unwrap();
+        let filter = index.build_key_filter().unwrap();
+        let mut count = 0;
+        for row in index.iter_without_row_id().unwrap() {
+            assert!(filter.might_contain(&row.unwrap().into_sorted_fields()));
+            count += 1;
+        }
+        assert_eq!(count, index.row_count().unwrap());
+    }
+
     #[test]
     fn test_search_with_index() {
         let db = DB::open("examples/string_index.db").unwrap();
@@ -240,4 +972,702 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_unique_autoindexes() {
+        assert_eq!(UsersPK::NAME, "sqlite_autoindex_users_1");
+        assert_eq!(UsersEmailUnique::NAME, "sqlite_autoindex_users_2");
+        assert_eq!(UsersNicknameUnique::NAME, "sqlite_autoindex_users_3");
+
+        let db = DB::open("examples/unique.db").unwrap();
+        let table = db.table::<Users>().unwrap();
+        let bob = Some(Users {
+            name: "bob".to_owned(),
+            email: "bob@example.com".to_owned(),
+            nickname: Some("bobby".to_owned()),
+        });
+        assert_eq!(
+            table
+                .get_with_index::<UsersPK>(&("bob".to_owned(),))
+                .unwrap(),
+            bob
+        );
+        assert_eq!(
+            table
+                .get_with_index::<UsersEmailUnique>(&("bob@example.com".to_owned(),))
+                .unwrap(),
+            bob
+        );
+        assert_eq!(
+            table
+                .get_with_index::<UsersNicknameUnique>(&(Some("bobby".to_owned()),))
+                .unwrap(),
+            bob
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    struct Pets {
+        #[table(row_id)]
+        #[serde(with = "row_id")]
+        id: i64,
+        species: String,
+        name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct PetsSpecies {
+        species: String,
+        key: i64,
+    }
+
+    impl Table for PetsSpecies {
+        const TYPE: SchemaType = SchemaType::Index;
+        const NAME: &'static str = "pets_species";
+    }
+
+    impl WithoutRowId for PetsSpecies {
+        type SortedFields = (String,);
+
+        fn into_sorted_fields(self) -> Self::SortedFields {
+            (self.species,)
+        }
+    }
+
+    impl Index<Pets> for PetsSpecies {
+        fn get_row_id(&self) -> i64 {
+            self.key
+        }
+    }
+
+    #[test]
+    fn test_get_all() {
+        let db = DB::open("examples/pets.db").unwrap();
+        let index = db.table::<PetsSpecies>().unwrap();
+
+        for (remainder, species) in [(0, "cat"), (1, "dog"), (2, "fish")] {
+            let key = (species.to_owned(),);
+            let ids = index
+                .get_all(&key)
+                .unwrap()
+                .map(|entry| entry.map(|entry| entry.key))
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            let expected = (1..=2000)
+                .filter(|id| id % 3 == remainder)
+                .collect::<Vec<_>>();
+            assert_eq!(ids, expected, "{species}");
+        }
+        assert_eq!(index.get_all(&("cow".to_owned(),)).unwrap().count(), 0);
+
+        let entries = index.iter_without_row_id().unwrap().count();
+        assert_eq!(entries, 2000);
+        let ids = index
+            .get(&("dog".to_owned(),)..&("fish".to_owned(),))
+            .unwrap()
+            .map(|entry| entry.unwrap().key)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, (1..=2000).step_by(3).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_descending_index() {
+        use std::cmp::Reverse;
+
+        #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+        struct ScoresByScore {
+            score: i64,
+            key: i64,
+        }
+
+        impl Table for ScoresByScore {
+            const TYPE: SchemaType = SchemaType::Index;
+            const NAME: &'static str = "scores_by_score";
+        }
+
+        impl WithoutRowId for ScoresByScore {
+            type SortedFields = (Reverse<i64>,);
+
+            fn into_sorted_fields(self) -> Self::SortedFields {
+                (Reverse(self.score),)
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+        #[table(without_rowid)]
+        struct Ranks {
+            #[table(primary_key)]
+            #[table(desc)]
+            name: String,
+            score: i64,
+        }
+
+        let db = DB::open("examples/descending.db").unwrap();
+
+        // The index stores the highest scores first.
+        let index = db.table::<ScoresByScore>().unwrap();
+        let scores = index
+            .get(&(Reverse(45),)..=&(Reverse(40),))
+            .unwrap()
+            .map(|entry| entry.map(|entry| entry.score))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(scores.len(), 296);
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!((scores[0], scores[295]), (45, 40));
+        assert_eq!(
+            index.get(&(Reverse(45),)).unwrap().map(|entry| entry.score),
+            Some(45)
+        );
+
+        let ranks = db.table::<Ranks>().unwrap();
+        let row = ranks
+            .get_by_key(&(Reverse("rank 0011".to_owned()),))
+            .unwrap();
+        assert_eq!(row.map(|row| row.score), Some(11));
+        let scores = ranks
+            .get(&(Reverse("rank 0012".to_owned()),)..=&(Reverse("rank 0010".to_owned()),))
+            .unwrap()
+            .map(|row| row.map(|row| row.score))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(scores, [12, 11, 10]);
+    }
+
+    #[test]
+    fn test_triggers() {
+        let db = DB::open("examples/triggers.db").unwrap();
+        let triggers = db.triggers().unwrap();
+        assert_eq!(
+            triggers,
+            [
+                (
+                    "items_insert".to_owned(),
+                    "items".to_owned(),
+                    "CREATE TRIGGER items_insert AFTER INSERT ON items BEGIN INSERT INTO audit VALUES (new.id, 'insert'); END".to_owned(),
+                ),
+                (
+                    "items_delete".to_owned(),
+                    "items".to_owned(),
+                    "CREATE TRIGGER items_delete AFTER DELETE ON items BEGIN INSERT INTO audit VALUES (old.id, 'delete'); END".to_owned(),
+                ),
+            ]
+        );
+
+        let db = DB::open("examples/empty.db").unwrap();
+        assert!(db.triggers().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_row_id_without_serde_attribute() {
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "numbers")]
+        struct PlainNumbers {
+            #[table(row_id)]
+            id: i64,
+            square: i64,
+            name: String,
+        }
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<PlainNumbers>().unwrap();
+        let row = table.get(12).unwrap().unwrap();
+        assert_eq!(
+            (row.id, row.square, row.name.as_str()),
+            (12, 144, "number 12")
+        );
+        assert_eq!(table.iter().unwrap().count(), 3000);
+    }
+
+    #[test]
+    fn test_row_id_alias_from_sql() {
+        // Without `#[table(row_id)]`, the alias is found from the table's SQL.
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "numbers")]
+        struct UnmarkedNumbers {
+            id: i64,
+            square: i64,
+            name: String,
+        }
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<UnmarkedNumbers>().unwrap();
+        let row = table.get(12).unwrap().unwrap();
+        assert_eq!(
+            (row.id, row.square, row.name.as_str()),
+            (12, 144, "number 12")
+        );
+        assert!(table
+            .iter()
+            .unwrap()
+            .enumerate()
+            .all(|(index, row)| row.unwrap().id == index as i64 + 1));
+        assert_eq!(table.get_many([7]).unwrap()[0].as_ref().unwrap().id, 7);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::fmt::{self, Write};
+
+        use tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        };
+
+        // Records each span and event as its name followed by its fields.
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                write!(self.0, " {}={value:?}", field.name()).unwrap();
+            }
+        }
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                let mut fields = Fields(span.metadata().name().to_owned());
+                span.record(&mut fields);
+                let mut log = self.0.lock().unwrap();
+                log.push(fields.0);
+                span::Id::from_u64(log.len() as u64)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields("event".to_owned());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let db = DB::open("examples/numbers.db").unwrap();
+            db.table::<Numbers>().unwrap().get(12).unwrap().unwrap();
+        });
+
+        let log = capture.0.lock().unwrap();
+        assert_eq!(log[0], "open base_offset=0");
+        let get = log
+            .iter()
+            .position(|line| line == "get table=\"numbers\"")
+            .unwrap();
+        assert!(log[get..]
+            .iter()
+            .any(|line| line.starts_with("seek row_id=12")));
+        assert!(log[get..]
+            .iter()
+            .any(|line| line.starts_with("event message=page access page_number=")));
+    }
+
+    #[test]
+    fn test_added_column() {
+        #[derive(Debug, PartialEq, Deserialize, Table)]
+        struct Tasks {
+            #[table(row_id)]
+            id: i64,
+            title: String,
+            done_at: Option<String>,
+        }
+
+        // The first two rows were inserted before done_at was added.
+        let db = DB::open("examples/altered.db").unwrap();
+        let rows = db.table::<Tasks>().unwrap().get_vec(..).unwrap();
+        assert_eq!(
+            rows,
+            [
+                Tasks {
+                    id: 1,
+                    title: "write".to_owned(),
+                    done_at: None,
+                },
+                Tasks {
+                    id: 2,
+                    title: "test".to_owned(),
+                    done_at: None,
+                },
+                Tasks {
+                    id: 3,
+                    title: "ship".to_owned(),
+                    done_at: Some("2024-01-02".to_owned()),
+                },
+            ]
+        );
+
+        let record = Record::from(db.btree_page(2).unwrap().leaf_table_cell(0).unwrap().1);
+        let row = <(Option<i64>, &str, Option<&str>)>::deserialize(record.borrowed_deserializer())
+            .unwrap();
+        assert_eq!(row, (None, "write", None));
+    }
+
+    #[test]
+    fn test_added_column_default() {
+        #[derive(Debug, PartialEq, Deserialize, Table)]
+        struct Counters {
+            #[table(row_id)]
+            id: i64,
+            name: String,
+            n: i64,
+            unit: String,
+        }
+
+        // The first two rows were inserted before n and unit were added.
+        let db = DB::open("examples/defaults.db").unwrap();
+        let rows = db.table::<Counters>().unwrap().get_vec(..).unwrap();
+        let values = rows
+            .iter()
+            .map(|row| (row.id, row.n, row.unit.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [(1, 0, "count"), (2, 0, "count"), (3, 512, "B")]);
+
+        let rows = db
+            .select_into::<serde_json::Value>("counters")
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            rows[0],
+            serde_json::json!({"id": 1, "name": "visits", "n": 0, "unit": "count"})
+        );
+    }
+
+    #[test]
+    fn test_column_count_mismatch() {
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "numbers")]
+        #[allow(dead_code)]
+        struct WideNumbers {
+            #[table(row_id)]
+            id: i64,
+            square: i64,
+            name: String,
+            cube: i64,
+        }
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        let err = db.table::<WideNumbers>().unwrap().get(12).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ColumnCountMismatch>(),
+            Some(&ColumnCountMismatch {
+                expected: 4,
+                found: 3,
+                table: "numbers",
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "expected 4 columns in table numbers but the record has 3"
+        );
+
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "numbers")]
+        #[allow(dead_code)]
+        struct NarrowNumbers {
+            #[table(row_id)]
+            id: i64,
+            square: i64,
+        }
+
+        let err = db.table::<NarrowNumbers>().unwrap().get(12).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ColumnCountMismatch>(),
+            Some(&ColumnCountMismatch {
+                expected: 2,
+                found: 3,
+                table: "numbers",
+            })
+        );
+    }
+
+    #[test]
+    fn test_too_many_fields() {
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "readings")]
+        #[allow(dead_code)]
+        struct Reading {
+            #[table(row_id)]
+            id: i64,
+            sensor: String,
+            taken_at: String,
+            value: f64,
+            unit: String,
+            note: Option<String>,
+        }
+
+        let db = DB::open("examples/readings.db").unwrap();
+        let table = db.table::<Reading>().unwrap();
+        let err = table.get(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected 6 columns in table readings but the record has 5"
+        );
+        assert!(table.iter().unwrap().all(|row| row.is_err()));
+    }
+
+    #[test]
+    fn test_tables() {
+        let db = DB::open("examples/tables.db").unwrap();
+        let tables = db
+            .tables()
+            .unwrap()
+            .into_iter()
+            .map(|table| (table.name, table.rootpage, table.row_count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tables,
+            [
+                ("authors".to_owned(), 2, 10),
+                ("books".to_owned(), 4, 1000),
+                ("tags".to_owned(), 6, 300),
+                // The shadow tables of the virtual table `search`, which is
+                // itself left out.
+                ("search_data".to_owned(), 74, 3),
+                ("search_idx".to_owned(), 75, 1),
+                ("search_content".to_owned(), 76, 1),
+                ("search_docsize".to_owned(), 77, 1),
+                ("search_config".to_owned(), 79, 1),
+            ]
+        );
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        assert_eq!(db.table::<Numbers>().unwrap().row_count().unwrap(), 3000);
+    }
+
+    #[test]
+    fn test_catalog() {
+        let mut catalog = catalog::Catalog::new();
+        catalog
+            .attach("main", DB::open("examples/numbers.db").unwrap())
+            .unwrap();
+        catalog
+            .attach("aux", DB::open("examples/pets.db").unwrap())
+            .unwrap();
+        assert!(catalog
+            .attach("aux", DB::open("examples/empty.db").unwrap())
+            .is_err());
+        assert_eq!(catalog.names().collect::<Vec<_>>(), ["main", "aux"]);
+
+        let twelve = catalog.table::<Numbers>("main").unwrap().get(12).unwrap();
+        assert_eq!(twelve.unwrap().square, 144);
+        let pets = catalog
+            .table::<Pets>("aux")
+            .unwrap()
+            .iter()
+            .unwrap()
+            .count();
+        assert!(pets > 0);
+
+        // Each table is only found in its own database.
+        assert!(catalog.table::<Pets>("main").is_err());
+        assert!(catalog.table::<Numbers>("temp").is_err());
+
+        assert!(catalog.detach("aux").is_some());
+        assert!(catalog.table::<Pets>("aux").is_err());
+    }
+
+    #[test]
+    fn test_get_vec() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let rows = table.get_vec(1..=10).unwrap();
+        let collected = table
+            .get(1..=10)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows, collected);
+        assert_eq!(rows.len(), 10);
+        assert!(rows.capacity() >= 10);
+
+        assert_eq!(table.get_vec(2990..).unwrap().len(), 11);
+        assert!(table.get_vec(5000..i64::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_project() {
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "crashes")]
+        struct Crash {
+            #[table(row_id)]
+            _id: i64,
+            _year: i32,
+            _lat: f64,
+            _lng: f64,
+            _severity: i32,
+            _total_vehicles: i32,
+        }
+
+        let db = DB::open("examples/crashes.db").unwrap();
+        let table = db.table::<Crash>().unwrap();
+        let rows = table
+            .project::<(i64, f64)>(&[0, 2])
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows, [(1, -36.8485), (2, -41.2865), (3, -43.5321)]);
+
+        // The columns do not have to be in table order.
+        let (year, id) = table
+            .project::<(i32, i64)>(&[1, 0])
+            .unwrap()
+            .nth(1)
+            .unwrap()
+            .unwrap();
+        assert_eq!((year, id), (2020, 2));
+
+        assert!(table
+            .project::<(i64, f64)>(&[0])
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_range_size_hint() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+
+        let mut rows = table.get(1000..2000).unwrap();
+        let (lower, upper) = rows.size_hint();
+        assert!(lower > 0);
+        assert_eq!(upper, Some(1000));
+
+        rows.nth(499).unwrap().unwrap();
+        assert_eq!(rows.size_hint().1, Some(500));
+        assert_eq!(rows.by_ref().count(), 500);
+        assert_eq!(rows.size_hint(), (0, Some(0)));
+
+        let mut rows = table.iter().unwrap();
+        assert_eq!(rows.size_hint(), (0, None));
+        assert_eq!(rows.by_ref().count(), 3000);
+        assert_eq!(rows.size_hint(), (0, Some(0)));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Table)]
+    struct People {
+        #[table(row_id)]
+        id: i64,
+        city: String,
+        role: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct PeopleCity {
+        city: String,
+        key: i64,
+    }
+
+    impl Table for PeopleCity {
+        const TYPE: SchemaType = SchemaType::Index;
+        const NAME: &'static str = "people_city";
+    }
+
+    impl WithoutRowId for PeopleCity {
+        type SortedFields = (String,);
+
+        fn into_sorted_fields(self) -> Self::SortedFields {
+            (self.city,)
+        }
+    }
+
+    impl Index<People> for PeopleCity {
+        fn get_row_id(&self) -> i64 {
+            self.key
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct PeopleRole {
+        role: i64,
+        key: i64,
+    }
+
+    impl Table for PeopleRole {
+        const TYPE: SchemaType = SchemaType::Index;
+        const NAME: &'static str = "people_role";
+    }
+
+    impl WithoutRowId for PeopleRole {
+        type SortedFields = (i64,);
+
+        fn into_sorted_fields(self) -> Self::SortedFields {
+            (self.role,)
+        }
+    }
+
+    impl Index<People> for PeopleRole {
+        fn get_row_id(&self) -> i64 {
+            self.key
+        }
+    }
+
+    #[test]
+    fn test_intersect_indexes() {
+        let db = DB::open("examples/people.db").unwrap();
+
+        let rows = db
+            .intersect_indexes::<People, PeopleCity, PeopleRole>(&("city 2".to_owned(),), &(3,))
+            .unwrap();
+        assert_eq!(
+            rows.iter().map(|row| row.id).collect::<Vec<_>>(),
+            (1..=700)
+                .filter(|id| id % 5 == 2 && id % 7 == 3)
+                .collect::<Vec<_>>()
+        );
+        assert!(rows.iter().all(|row| row.city == "city 2" && row.role == 3));
+
+        let rows = db
+            .intersect_indexes::<People, PeopleCity, PeopleRole>(&("city 2".to_owned(),), &(9,))
+            .unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_min_max() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let table = db.table::<Numbers>().unwrap();
+        let ids = table
+            .iter()
+            .unwrap()
+            .map(|row| row.unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(table.min_row_id().unwrap(), ids.iter().copied().min());
+        assert_eq!(table.max_row_id().unwrap(), ids.iter().copied().max());
+
+        let db = DB::open("examples/people.db").unwrap();
+        let index = db.table::<PeopleCity>().unwrap();
+        assert_eq!(
+            index.rootpage().unwrap().page_type(),
+            BTreePageType::InteriorIndex
+        );
+        let entries = index
+            .iter_without_row_id()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(index.min_key().unwrap(), entries.iter().min().cloned());
+        assert_eq!(index.max_key().unwrap(), entries.iter().max().cloned());
+
+        let db = DB::open("examples/empty.db").unwrap();
+        let table = db.table::<Empty>().unwrap();
+        assert_eq!(table.min_row_id().unwrap(), None);
+        assert_eq!(table.max_row_id().unwrap(), None);
+    }
 }
@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use super::{
+    deserialize_record_with_row_id,
+    record::{Record, SerialValue},
+    TableHandle, WithRowId,
+};
+
+/// A column of a record, for building predicates for [`TableHandle::filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column(usize);
+
+/// Refers to the column at `index`, counting from 0 in the order of the
+/// `CREATE TABLE` statement.
+pub fn col(index: usize) -> Column {
+    Column(index)
+}
+
+impl Column {
+    /// Matches records whose value in this column equals `value`. Integers
+    /// and reals are compared by value, as in SQL, and NULL equals nothing.
+    /// Columns past the end of the record are NULL.
+    ///
+    /// `INTEGER PRIMARY KEY` columns are stored as NULL, so they never match;
+    /// filter on the row id instead.
+    pub fn eq(self, value: SerialValue) -> impl Fn(&Record) -> bool {
+        move |record| match record.project(&[self.0]) {
+            Ok(values) => values_equal(&values[0], &value),
+            // The error is reported when the row is deserialized instead.
+            Err(_) => true,
+        }
+    }
+}
+
+fn values_equal(a: &SerialValue, b: &SerialValue) -> bool {
+    match (a, b) {
+        (SerialValue::Null, _) | (_, SerialValue::Null) => false,
+        (SerialValue::F64(a), SerialValue::F64(b)) => a.get() == b.get(),
+        (SerialValue::F64(real), other) | (other, SerialValue::F64(real)) => other
+            .as_i64()
+            .is_some_and(|integer| integer as f64 == real.get()),
+        _ => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+    }
+}
+
+impl<T: WithRowId> TableHandle<T> {
+    /// Iterates over the rows for which `predicate` holds, along with their
+    /// row ids. The predicate is checked against the undecoded record, so
+    /// rows that do not match are never deserialized. See [`col`] for
+    /// building predicates.
+    pub fn filter(
+        &self,
+        predicate: impl Fn(&Record) -> bool,
+    ) -> Result<impl Iterator<Item = Result<(i64, T)>>> {
+        let layout = self.layout.clone();
+        let records = self.rootpage()?.into_table_entries_range(None..None)?;
+        let rows = records
+            .filter(move |record| match record {
+                Ok((_, data)) => predicate(&Record::from(data.clone())),
+                Err(_) => true,
+            })
+            .map(move |record| {
+                let (row_id, data) = record?;
+                let row = deserialize_record_with_row_id(&layout, (row_id, data))?;
+                Ok((row_id, row))
+            });
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{
+        physical::db::DB,
+        schema::{SchemaType, Table},
+    };
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, Table)]
+    #[table(name = "crashes")]
+    struct Crash {
+        #[table(row_id)]
+        id: i64,
+        year: i32,
+        _lat: f64,
+        _lng: f64,
+        severity: i32,
+        _total_vehicles: i32,
+    }
+
+    const YEAR: usize = 1;
+    const LAT: usize = 2;
+    const SEVERITY: usize = 4;
+
+    fn matching_ids(table: &TableHandle<Crash>, predicate: impl Fn(&Record) -> bool) -> Vec<i64> {
+        table
+            .filter(predicate)
+            .unwrap()
+            .map(|row| row.map(|(row_id, _)| row_id))
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        let db = DB::open("examples/crashes.db").unwrap();
+        let table = db.table::<Crash>().unwrap();
+
+        let rows = table
+            .filter(col(SEVERITY).eq(SerialValue::from_i64(3)))
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        let (row_id, crash) = &rows[0];
+        assert_eq!((*row_id, crash.id), (3, 3));
+        assert_eq!((crash.year, crash.severity), (2021, 3));
+
+        // Integers and reals compare by value.
+        assert_eq!(
+            matching_ids(&table, col(YEAR).eq(SerialValue::from_f64(2020.0))),
+            [2]
+        );
+        assert_eq!(
+            matching_ids(&table, col(LAT).eq(SerialValue::from_f64(-43.5321))),
+            [3]
+        );
+
+        assert!(matching_ids(&table, col(SEVERITY).eq(SerialValue::from_i64(9))).is_empty());
+        assert!(matching_ids(&table, col(SEVERITY).eq(SerialValue::Null)).is_empty());
+        assert!(matching_ids(&table, col(10).eq(SerialValue::from_i64(3))).is_empty());
+    }
+}
@@ -2,10 +2,13 @@ use std::{
     cmp::Ordering,
     iter::Map,
     marker::PhantomData,
-    ops::{Bound, Range, RangeBounds, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive},
+    ops::{
+        Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive,
+    },
 };
 
 use anyhow::Result;
+use serde::de::{value::SeqDeserializer, DeserializeOwned, IntoDeserializer};
 
 use crate::physical::{
     btree::iter::{BTreeIndexEntries, BTreeTableEntries},
@@ -13,7 +16,9 @@ use crate::physical::{
 };
 
 use super::{
-    deserialize_record, deserialize_record_with_row_id, Table, TableHandle, WithRowId, WithoutRowId,
+    deserialize_record, deserialize_record_with_row_id,
+    record::{Record, SerialValue},
+    Table, TableHandle, TableLayout, WithRowId, WithoutRowId,
 };
 
 pub trait TableRange<T: Table> {
@@ -29,34 +34,62 @@ pub struct IndexComparator<I, T> {
 
 struct EqComparator;
 
-type MappedTableEntries<T> = Map<BTreeTableEntries, fn(Result<(u64, ArcBufSlice)>) -> Result<T>>;
+// Ranges of row ids can be far larger than the table, so only small ranges
+// are allocated for in advance.
+const MAX_PRESIZE: usize = 1 << 16;
+
+/// The rows of a table in a range of row ids. See [`TableHandle::get`].
+pub struct TableRows<T> {
+    entries: BTreeTableEntries,
+    layout: TableLayout,
+    _marker: PhantomData<T>,
+}
 
 type MappedIndexEntries<T, C> = Map<BTreeIndexEntries<C>, fn(Result<ArcBufSlice>) -> Result<T>>;
 
 fn table_range_impl<T: WithRowId>(
     table: &TableHandle<T>,
-    range: impl RangeBounds<u64>,
-) -> Result<MappedTableEntries<T>> {
+    range: impl RangeBounds<i64>,
+) -> Result<TableRows<T>> {
     let start = match range.start_bound() {
         Bound::Included(&start) => Some(start),
-        Bound::Excluded(&start) => Some(start + 1),
+        Bound::Excluded(&start) => Some(start.saturating_add(1)),
         Bound::Unbounded => None,
     };
-    let end = match range.end_bound() {
-        Bound::Included(&end) => Some(end + 1),
-        Bound::Excluded(&end) => Some(end),
-        Bound::Unbounded => None,
+    let end = match (range.start_bound(), range.end_bound()) {
+        // Nothing comes after i64::MAX, so the range is empty.
+        (Bound::Excluded(&i64::MAX), _) => start,
+        // There is no exclusive bound past i64::MAX, so leave the range open.
+        (_, Bound::Included(&end)) => end.checked_add(1),
+        (_, Bound::Excluded(&end)) => Some(end),
+        (_, Bound::Unbounded) => None,
     };
 
-    let records = table.rootpage()?.into_table_entries_range(start..end)?;
-    let rows = records.map::<_, fn(_) -> _>(|record| deserialize_record_with_row_id(record?));
-    Ok(rows)
+    Ok(TableRows {
+        entries: table.rootpage()?.into_table_entries_range(start..end)?,
+        layout: table.layout.clone(),
+        _marker: PhantomData,
+    })
+}
+
+impl<T: WithRowId> Iterator for TableRows<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.entries.next()?;
+        Some(record.and_then(|record| deserialize_record_with_row_id(&self.layout, record)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
 }
 
 fn index_range_impl<I: WithoutRowId, C: PartialOrd<ArcBufSlice>>(
     index: &TableHandle<I>,
     comparator: C,
 ) -> Result<MappedIndexEntries<I, C>> {
+    index.layout.check_column_count::<I>()?;
     let records = index.rootpage()?.into_index_entries_range(comparator)?;
     let rows = records.map::<_, fn(_) -> _>(|record| deserialize_record(record?));
     Ok(rows)
@@ -84,7 +117,7 @@ fn range_cmp<'a, T: Ord + 'a>(range: &impl RangeBounds<&'a T>, other: &T) -> Ord
             }
         }
         Bound::Excluded(&end) => {
-            if end >= other {
+            if end <= other {
                 return Ordering::Less;
             }
         }
@@ -107,8 +140,8 @@ fn index_cmp_impl<'a, I: WithoutRowId + 'a>(
 macro_rules! impl_for_range_types {
     ($($range:ident),*) => {
         $(
-            impl<T: WithRowId> TableRange<T> for $range<u64> {
-                type Output = MappedTableEntries<T>;
+            impl<T: WithRowId> TableRange<T> for $range<i64> {
+                type Output = TableRows<T>;
 
                 fn range(self, table: &TableHandle<T>) -> Result<Self::Output> {
                     table_range_impl(table, self)
@@ -144,11 +177,19 @@ impl PartialOrd<ArcBufSlice> for EqComparator {
     }
 }
 
-impl<T: WithRowId> TableRange<T> for u64 {
+impl<T: WithRowId> TableRange<T> for RangeFull {
+    type Output = TableRows<T>;
+
+    fn range(self, table: &TableHandle<T>) -> Result<Self::Output> {
+        table_range_impl(table, self)
+    }
+}
+
+impl<T: WithRowId> TableRange<T> for i64 {
     type Output = Option<T>;
 
     fn range(self, table: &TableHandle<T>) -> Result<Self::Output> {
-        table_range_impl(table, self..)?.next().transpose()
+        table_range_impl(table, self..=self)?.next().transpose()
     }
 }
 
@@ -182,9 +223,31 @@ where
 
 impl<T: Table> TableHandle<T> {
     pub fn get<R: TableRange<T>>(&self, id: R) -> Result<R::Output> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("get", table = T::NAME).entered();
+
         id.range(self)
     }
 
+    /// Like [`TableHandle::get`], but collects the rows, stopping at the first
+    /// error. For a range of row ids, the `Vec` is sized up front from the
+    /// number of ids in the range, as long as that is not too large.
+    pub fn get_vec<R>(&self, range: R) -> Result<Vec<T>>
+    where
+        R: TableRange<T>,
+        R::Output: Iterator<Item = Result<T>>,
+    {
+        let rows = self.get(range)?;
+        let (lower, upper) = rows.size_hint();
+        let capacity = upper.filter(|&upper| upper <= MAX_PRESIZE).unwrap_or(lower);
+
+        let mut vec = Vec::with_capacity(capacity);
+        for row in rows {
+            vec.push(row?);
+        }
+        Ok(vec)
+    }
+
     pub fn iter(&self) -> Result<impl Iterator<Item = Result<T>>>
     where
         T: WithRowId,
@@ -192,6 +255,132 @@ impl<T: Table> TableHandle<T> {
         table_range_impl(self, ..)
     }
 
+    /// Iterates over rows in row id order while `predicate` holds for the row
+    /// id. The b-tree walk stops at the first row id that fails, so later
+    /// pages are never read, and rows are only deserialized if they pass.
+    pub fn take_while_id(
+        &self,
+        mut predicate: impl FnMut(i64) -> bool,
+    ) -> Result<impl Iterator<Item = Result<T>>>
+    where
+        T: WithRowId,
+    {
+        let layout = self.layout.clone();
+        let records = self.rootpage()?.into_table_entries_range(None..None)?;
+        let rows = records
+            .take_while(move |record| match record {
+                Ok((row_id, _)) => predicate(*row_id),
+                Err(_) => true,
+            })
+            .map(move |record| deserialize_record_with_row_id(&layout, record?));
+        Ok(rows)
+    }
+
+    /// Reads only the given columns of each row, in the order given, and
+    /// deserializes them into `P`, such as a tuple or a smaller struct. The
+    /// other columns are skipped without being decoded.
+    pub fn project<P: DeserializeOwned>(
+        &self,
+        columns: &[usize],
+    ) -> Result<impl Iterator<Item = Result<P>>>
+    where
+        T: WithRowId,
+    {
+        let columns = columns.to_vec();
+        let row_id_column = T::ROW_ID_COLUMN.or(self.layout.row_id_column);
+        let records = self.rootpage()?.into_table_entries_range(None..None)?;
+        let rows = records.map(move |record| {
+            let (row_id, data) = record?;
+            let mut values = Record::from(data).project(&columns)?;
+            for (value, &column) in values.iter_mut().zip(&columns) {
+                if Some(column) == row_id_column && *value == SerialValue::Null {
+                    *value = SerialValue::from_i64(row_id);
+                }
+            }
+            Ok(P::deserialize(SeqDeserializer::new(values.into_iter()))?)
+        });
+        Ok(rows)
+    }
+
+    /// Looks up several rows at once, returning them in the same order as
+    /// `ids`. The ids are visited in sorted order so that the b-tree is only
+    /// walked once.
+    pub fn get_many(&self, ids: impl IntoIterator<Item = i64>) -> Result<Vec<Option<T>>>
+    where
+        T: WithRowId,
+    {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        let mut order = (0..ids.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&position| ids[position]);
+
+        let mut entries = self.rootpage()?.into_table_entries_range(None..None)?;
+        let mut found: Option<(i64, ArcBufSlice)> = None;
+        let mut rows = (0..ids.len()).map(|_| None).collect::<Vec<_>>();
+        for position in order {
+            let id = ids[position];
+            if !matches!(found, Some((found_id, _)) if found_id == id) {
+                entries.seek_forward(id)?;
+                found = entries.next().transpose()?;
+            }
+
+            if let Some((found_id, record)) = &found {
+                if *found_id == id {
+                    rows[position] = Some(deserialize_record_with_row_id(
+                        &self.layout,
+                        (id, record.clone()),
+                    )?);
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Finds every entry whose sorted fields equal `key`. Unlike
+    /// [`TableHandle::get`], this returns all matches of a non-unique index.
+    pub fn get_all<'a>(
+        &self,
+        key: &'a T::SortedFields,
+    ) -> Result<impl Iterator<Item = Result<T>> + 'a>
+    where
+        T: WithoutRowId + 'a,
+    {
+        self.get(key..=key)
+    }
+
+    /// Reads the entries of an index in `range` straight into `P`, without
+    /// looking up the rows of the table, for when the index covers every
+    /// column that is needed. `P` is read from the leading columns of each
+    /// entry, so any it does not have fields for, such as the row id, are
+    /// skipped.
+    pub fn scan_covering<P, R>(&self, range: R) -> Result<impl Iterator<Item = Result<P>>>
+    where
+        T: WithoutRowId,
+        P: DeserializeOwned,
+        IndexComparator<T, R>: PartialOrd<ArcBufSlice>,
+    {
+        let comparator = IndexComparator {
+            inner: range,
+            _marker: PhantomData::<T>,
+        };
+        let records = self.rootpage()?.into_index_entries_range(comparator)?;
+        let rows = records.map(|record| {
+            let deserializer = Record::from(record?)
+                .into_deserializer()
+                .ignoring_trailing();
+            Ok(P::deserialize(deserializer)?)
+        });
+        Ok(rows)
+    }
+
+    /// Looks up a row of a `WITHOUT ROWID` table by its primary key.
+    pub fn get_by_key(&self, key: &T::SortedFields) -> Result<Option<T>>
+    where
+        T: WithoutRowId,
+    {
+        self.get(key)
+    }
+
     pub fn iter_without_row_id(&self) -> Result<impl Iterator<Item = Result<T>>>
     where
         T: WithoutRowId,
@@ -1,12 +1,24 @@
 use std::fmt;
 
-#[derive(Clone, Copy, PartialEq, Eq, zerocopy::FromZeroes, zerocopy::FromBytes)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, zerocopy::FromZeroes, zerocopy::FromBytes, zerocopy::AsBytes,
+)]
+#[repr(transparent)]
 pub struct I24([u8; 3]);
 
-#[derive(Clone, Copy, PartialEq, Eq, zerocopy::FromZeroes, zerocopy::FromBytes)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, zerocopy::FromZeroes, zerocopy::FromBytes, zerocopy::AsBytes,
+)]
+#[repr(transparent)]
 pub struct I48([u8; 6]);
 
 impl I24 {
+    /// Truncates the value to 24 bits.
+    pub fn new(value: i32) -> Self {
+        let bytes = value.to_be_bytes();
+        Self([bytes[1], bytes[2], bytes[3]])
+    }
+
     pub fn get(&self) -> i32 {
         let bytes = self.0;
         let sign_extend = if bytes[0] & 0x80 == 0 { 0 } else { 0xff };
@@ -15,6 +27,12 @@ impl I24 {
 }
 
 impl I48 {
+    /// Truncates the value to 48 bits.
+    pub fn new(value: i64) -> Self {
+        let bytes = value.to_be_bytes();
+        Self([bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+    }
+
     pub fn get(&self) -> i64 {
         let bytes = self.0;
         let sign_extend = if bytes[0] & 0x80 == 0 { 0 } else { 0xff };
@@ -1,23 +1,60 @@
+use anyhow::{anyhow, Error, Result};
+
 use crate::physical::{buf::ArcBufSlice, varint};
 
-use super::{SerialType, SerialValue};
+use super::{SerialType, SerialValue, ValueRef};
 
 pub struct SerialTypeIterator {
     header_len: u64,
     data: ArcBufSlice,
+    // A malformed header is reported by the next call to `next`.
+    error: Option<Error>,
 }
 
 pub struct SerialValueIterator {
     types: SerialTypeIterator,
     data: ArcBufSlice,
+    lossy: bool,
+}
+
+/// Iterates over the values of a record, borrowing text and blobs from the
+/// record instead of copying them.
+pub struct BorrowedValueIterator<'a> {
+    values: SerialValueIterator,
+    record: &'a [u8],
 }
 
 impl SerialTypeIterator {
     pub(super) fn new(mut data: ArcBufSlice) -> Self {
-        let (header_len, len) = varint::read(&data);
-        data.truncate(header_len as usize);
-        data.consume_bytes(len);
-        Self { header_len, data }
+        let header = varint::read(&data).and_then(|(header_len, len)| {
+            if header_len < len as u64 || header_len > data.len() as u64 {
+                return Err(anyhow!(
+                    "record header length {header_len} does not fit in a {}-byte record",
+                    data.len()
+                ));
+            }
+            Ok((header_len, len))
+        });
+
+        match header {
+            Ok((header_len, len)) => {
+                data.truncate(header_len as usize);
+                data.consume_bytes(len);
+                Self {
+                    header_len,
+                    data,
+                    error: None,
+                }
+            }
+            Err(err) => {
+                data.truncate(0);
+                Self {
+                    header_len: 0,
+                    data,
+                    error: Some(err),
+                }
+            }
+        }
     }
 }
 
@@ -25,17 +62,64 @@ impl SerialValueIterator {
     pub(super) fn new(mut data: ArcBufSlice) -> Self {
         let types = SerialTypeIterator::new(data.clone());
         data.consume_bytes(types.header_len as usize);
-        Self { types, data }
+        Self {
+            types,
+            data,
+            lossy: false,
+        }
+    }
+
+    /// Reads the next serial type, checking that the body of the value fits
+    /// in the rest of the record.
+    fn next_type(&mut self) -> Option<Result<SerialType>> {
+        let ty = match self.types.next()? {
+            Ok(ty) => ty,
+            Err(err) => return Some(Err(err)),
+        };
+        if ty.body_len() > self.data.len() as u64 {
+            // Later values cannot be found either.
+            self.types.data.truncate(0);
+            return Some(Err(anyhow!("record value runs past the end of the record")));
+        }
+        Some(Ok(ty))
+    }
+
+    /// Steps over the next value, returning its type and body without
+    /// decoding it, so that it can be decoded with [`SerialValue::consume`]
+    /// only if it turns out to be needed.
+    pub(crate) fn next_raw(&mut self) -> Option<Result<(SerialType, ArcBufSlice)>> {
+        let ty = match self.next_type()? {
+            Ok(ty) => ty,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut body = self.data.clone();
+        body.truncate(ty.body_len() as usize);
+        self.data.consume_bytes(ty.body_len() as usize);
+        Some(Ok((ty, body)))
+    }
+
+    /// Replaces invalid UTF-8 in text values with U+FFFD instead of returning
+    /// an error.
+    pub fn lossy(mut self) -> Self {
+        self.lossy = true;
+        self
     }
 }
 
 impl Iterator for SerialTypeIterator {
-    type Item = SerialType;
+    type Item = Result<SerialType>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if !self.data.is_empty() {
-            let ty = self.data.consume_varint();
-            Some(SerialType::from(ty))
+        if let Some(err) = self.error.take() {
+            Some(Err(err))
+        } else if !self.data.is_empty() {
+            let ty = self.data.consume_varint().and_then(SerialType::try_from);
+            if ty.is_err() {
+                // The rest of the header cannot be read, or the values after
+                // this one cannot be located.
+                self.data.truncate(0);
+            }
+            Some(ty)
         } else {
             None
         }
@@ -43,14 +127,74 @@ impl Iterator for SerialTypeIterator {
 }
 
 impl Iterator for SerialValueIterator {
-    type Item = SerialValue;
+    type Item = Result<SerialValue>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(ty) = self.types.next() {
-            let value = SerialValue::consume(ty, &mut self.data);
+        if let Some(ty) = self.next_type() {
+            let ty = match ty {
+                Ok(ty) => ty,
+                Err(err) => return Some(Err(err)),
+            };
+            let value = if self.lossy {
+                Ok(SerialValue::consume_lossy(ty, &mut self.data))
+            } else {
+                SerialValue::consume(ty, &mut self.data)
+            };
             Some(value)
         } else {
             None
         }
     }
+
+    /// Skips over the first `n` values without decoding their bodies.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            match self.next_type()? {
+                Ok(ty) => self.data.consume_bytes(ty.body_len() as usize),
+                Err(err) => return Some(Err(err)),
+            };
+        }
+        self.next()
+    }
+
+    fn count(self) -> usize {
+        self.types.count()
+    }
+}
+
+impl<'a> BorrowedValueIterator<'a> {
+    pub(super) fn new(record: &'a ArcBufSlice) -> Self {
+        Self {
+            values: SerialValueIterator::new(record.clone()),
+            record,
+        }
+    }
+}
+
+impl<'a> Iterator for BorrowedValueIterator<'a> {
+    type Item = Result<ValueRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ty = match self.values.next_type()? {
+            Ok(ty) => ty,
+            Err(err) => return Some(Err(err)),
+        };
+
+        let value = match ty {
+            SerialType::Text(n) | SerialType::Blob(n) => {
+                let start = self.record.len() - self.values.data.len();
+                self.values.data.consume_bytes(n as usize);
+                let body = &self.record[start..start + n as usize];
+                if let SerialType::Text(_) = ty {
+                    std::str::from_utf8(body)
+                        .map(ValueRef::Text)
+                        .map_err(Into::into)
+                } else {
+                    Ok(ValueRef::Blob(body))
+                }
+            }
+            ty => SerialValue::consume(ty, &mut self.values.data).map(ValueRef::Owned),
+        };
+        Some(value)
+    }
 }
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{Number, Value};
+
+use super::SerialValue;
+
+/// Converts a value to JSON. Blobs are encoded as base64 strings, and floats
+/// that JSON cannot represent (NaN and infinities) become null.
+impl From<&SerialValue> for Value {
+    fn from(value: &SerialValue) -> Self {
+        match value {
+            SerialValue::Null => Value::Null,
+            SerialValue::I8(value) => (*value).into(),
+            SerialValue::I16(value) => value.get().into(),
+            SerialValue::I24(value) => value.get().into(),
+            SerialValue::I32(value) => value.get().into(),
+            SerialValue::I48(value) => value.get().into(),
+            SerialValue::I64(value) => value.get().into(),
+            SerialValue::F64(value) => {
+                Number::from_f64(value.get()).map_or(Value::Null, Value::Number)
+            }
+            SerialValue::Zero => 0.into(),
+            SerialValue::One => 1.into(),
+            SerialValue::Blob(value) => STANDARD.encode(value).into(),
+            SerialValue::Text(value) => value.clone().into(),
+        }
+    }
+}
+
+/// Converts JSON to a value. Integers use the smallest serial type that can
+/// hold them, and booleans are stored as 0 or 1. Strings always become text,
+/// as base64-encoded blobs cannot be told apart from other strings.
+impl TryFrom<&Value> for SerialValue {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(SerialValue::Null),
+            Value::Bool(value) => Ok(SerialValue::from_i64(*value as i64)),
+            Value::Number(number) => match number.as_i64() {
+                Some(value) => Ok(SerialValue::from_i64(value)),
+                None => Ok(SerialValue::from_f64(number.as_f64().unwrap())),
+            },
+            Value::String(value) => Ok(SerialValue::Text(value.clone())),
+            Value::Array(_) | Value::Object(_) => Err(anyhow!(
+                "JSON arrays and objects cannot be stored in a column"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use zerocopy::big_endian::{F64, I16, I32, I64};
+
+    use crate::schema::record::ints::{I24, I48};
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let values = [
+            (SerialValue::Null, json!(null)),
+            (SerialValue::I8(-5), json!(-5)),
+            (SerialValue::I16(I16::new(1000)), json!(1000)),
+            (SerialValue::I24(I24::new(-100_000)), json!(-100_000)),
+            (SerialValue::I32(I32::new(10_000_000)), json!(10_000_000)),
+            (SerialValue::I48(I48::new(1 << 40)), json!(1_i64 << 40)),
+            (SerialValue::I64(I64::new(i64::MIN)), json!(i64::MIN)),
+            (SerialValue::F64(F64::new(1.5)), json!(1.5)),
+            (SerialValue::Zero, json!(0)),
+            (SerialValue::One, json!(1)),
+            (SerialValue::Text("hello".to_owned()), json!("hello")),
+        ];
+
+        for (value, expected) in values {
+            let converted = Value::from(&value);
+            assert_eq!(converted, expected);
+            assert_eq!(SerialValue::try_from(&converted).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_blob_as_base64() {
+        let value = Value::from(&SerialValue::Blob(b"squeak".to_vec()));
+        assert_eq!(value, json!("c3F1ZWFr"));
+        assert_eq!(
+            SerialValue::try_from(&value).unwrap(),
+            SerialValue::Text("c3F1ZWFr".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_non_finite_float() {
+        assert_eq!(
+            Value::from(&SerialValue::F64(F64::new(f64::NAN))),
+            json!(null)
+        );
+        assert!(SerialValue::try_from(&json!([1])).is_err());
+        assert_eq!(
+            SerialValue::try_from(&json!(true)).unwrap(),
+            SerialValue::One
+        );
+    }
+}
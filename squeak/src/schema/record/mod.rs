@@ -1,22 +1,41 @@
 use std::fmt;
 
-use zerocopy::big_endian::{F64, I16, I32, I64};
+use anyhow::{anyhow, Result};
+use zerocopy::{
+    big_endian::{F64, I16, I32, I64},
+    AsBytes,
+};
 
-use crate::physical::buf::ArcBufSlice;
+use crate::{
+    physical::{
+        buf::{ArcBuf, ArcBufSlice},
+        varint,
+    },
+    schema::sql::Affinity,
+};
 
 use self::{
     ints::{I24, I48},
-    iter::{SerialTypeIterator, SerialValueIterator},
+    iter::{BorrowedValueIterator, SerialTypeIterator, SerialValueIterator},
 };
 
 pub mod ints;
 pub mod iter;
+#[cfg(feature = "json")]
+mod json;
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Record {
     data: ArcBufSlice,
 }
 
+/// A fully decoded record that does not hold a reference to the page it was
+/// read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedRecord {
+    values: Vec<SerialValue>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SerialType {
     Null,
@@ -49,13 +68,71 @@ pub enum SerialValue {
     Text(String),
 }
 
+/// A value that borrows text and blobs from the record it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    Text(&'a str),
+    Blob(&'a [u8]),
+    /// Any other value, which holds no data worth borrowing.
+    Owned(SerialValue),
+}
+
 impl From<ArcBufSlice> for Record {
     fn from(data: ArcBufSlice) -> Self {
         Self { data }
     }
 }
 
+// The most columns a table can have, even in builds of SQLite with the
+// limit raised.
+const MAX_COLUMNS: usize = 32767;
+
 impl Record {
+    /// Copies a record from untrusted bytes, checking up front that the
+    /// header and every value fit in the record, that the values end where
+    /// the record does, that no reserved serial types are used, and that text
+    /// is valid UTF-8. [`Record::from`] leaves these checks until the values
+    /// are read.
+    pub fn try_parse(data: &[u8]) -> Result<Record> {
+        let (header_len, len) = varint::read(data)?;
+        if header_len < len as u64 || header_len > data.len() as u64 {
+            return Err(anyhow!(
+                "record header length {header_len} does not fit in a {}-byte record",
+                data.len()
+            ));
+        }
+
+        let mut header = &data[len..header_len as usize];
+        let mut body = &data[header_len as usize..];
+        let mut columns = 0;
+        while !header.is_empty() {
+            columns += 1;
+            if columns > MAX_COLUMNS {
+                return Err(anyhow!("record has more than {MAX_COLUMNS} columns"));
+            }
+
+            let (ty, len) = varint::read(header)?;
+            header = &header[len..];
+            let ty = SerialType::try_from(ty)?;
+            if ty.body_len() > body.len() as u64 {
+                return Err(anyhow!("record value runs past the end of the record"));
+            }
+            let (value, rest) = body.split_at(ty.body_len() as usize);
+            if let SerialType::Text(_) = ty {
+                std::str::from_utf8(value)?;
+            }
+            body = rest;
+        }
+        if !body.is_empty() {
+            return Err(anyhow!(
+                "record has {} bytes after its last value",
+                body.len()
+            ));
+        }
+
+        Ok(Record::from(ArcBufSlice::from(ArcBuf::from(data))))
+    }
+
     pub fn types(&self) -> SerialTypeIterator {
         self.clone().into_types()
     }
@@ -64,6 +141,11 @@ impl Record {
         self.clone().into_values()
     }
 
+    /// Like [`Record::values`], but borrows text and blobs from the record.
+    pub fn borrowed_values(&self) -> BorrowedValueIterator<'_> {
+        BorrowedValueIterator::new(&self.data)
+    }
+
     pub fn into_types(self) -> SerialTypeIterator {
         SerialTypeIterator::new(self.data)
     }
@@ -71,19 +153,103 @@ impl Record {
     pub fn into_values(self) -> SerialValueIterator {
         SerialValueIterator::new(self.data)
     }
+
+    /// Decodes every value, so that the record can outlive its page.
+    pub fn to_owned_record(&self) -> Result<OwnedRecord> {
+        let values = self.values().collect::<Result<_>>()?;
+        Ok(OwnedRecord { values })
+    }
+
+    /// Decodes only the values of the given columns, in the order given.
+    /// Other columns are skipped without being decoded, and columns past the
+    /// end of the record are read as NULL.
+    pub fn project(&self, columns: &[usize]) -> Result<Vec<SerialValue>> {
+        let mut sorted = columns.iter().copied().enumerate().collect::<Vec<_>>();
+        sorted.sort_by_key(|&(_, column)| column);
+
+        let mut projected = vec![SerialValue::Null; columns.len()];
+        let mut values = self.values();
+        let mut next_column = 0;
+        let mut previous: Option<(usize, usize)> = None;
+        for (position, column) in sorted {
+            if let Some((previous_position, previous_column)) = previous {
+                if previous_column == column {
+                    projected[position] = projected[previous_position].clone();
+                    continue;
+                }
+            }
+
+            if let Some(value) = values.nth(column - next_column) {
+                projected[position] = value?;
+            }
+            next_column = column + 1;
+            previous = Some((position, column));
+        }
+
+        Ok(projected)
+    }
+
+    /// Encodes the values as a record: a header of serial types followed by
+    /// the value bodies.
+    pub fn build(values: &[SerialValue]) -> Vec<u8> {
+        let mut types = Vec::new();
+        for value in values {
+            varint::write(value.serial_type().into(), &mut types);
+        }
+
+        // The header length includes the varint that encodes it.
+        let mut header_len = types.len() as u64 + 1;
+        let mut header = Vec::new();
+        while varint::write(header_len, &mut header) + types.len() != header_len as usize {
+            header_len += 1;
+            header.clear();
+        }
+        header.extend_from_slice(&types);
+
+        for value in values {
+            value.write_body(&mut header);
+        }
+        header
+    }
 }
 
 impl fmt::Debug for Record {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Record")
-            .field("columns", &self.values().collect::<Vec<_>>())
+            .field(
+                "columns",
+                &self.values().lossy().flatten().collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
 
-impl From<u64> for SerialType {
-    fn from(value: u64) -> Self {
-        match value {
+impl OwnedRecord {
+    pub fn values(&self) -> &[SerialValue] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<SerialValue> {
+        self.values
+    }
+
+    /// Encodes the values as a record. See [`Record::build`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Record::build(&self.values)
+    }
+}
+
+impl From<Vec<SerialValue>> for OwnedRecord {
+    fn from(values: Vec<SerialValue>) -> Self {
+        Self { values }
+    }
+}
+
+impl TryFrom<u64> for SerialType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u64) -> Result<Self> {
+        Ok(match value {
             0 => Self::Null,
             1 => Self::I8,
             2 => Self::I16,
@@ -94,15 +260,174 @@ impl From<u64> for SerialType {
             7 => Self::F64,
             8 => Self::Zero,
             9 => Self::One,
-            10 | 11 => panic!("encountered internal use column type"),
+            10 | 11 => return Err(anyhow!("record uses reserved serial type {value}")),
             n if n % 2 == 0 => Self::Blob((n - 12) / 2),
             n => Self::Text((n - 13) / 2),
+        })
+    }
+}
+
+impl From<SerialType> for u64 {
+    fn from(ty: SerialType) -> Self {
+        match ty {
+            SerialType::Null => 0,
+            SerialType::I8 => 1,
+            SerialType::I16 => 2,
+            SerialType::I24 => 3,
+            SerialType::I32 => 4,
+            SerialType::I48 => 5,
+            SerialType::I64 => 6,
+            SerialType::F64 => 7,
+            SerialType::Zero => 8,
+            SerialType::One => 9,
+            SerialType::Blob(n) => n * 2 + 12,
+            SerialType::Text(n) => n * 2 + 13,
+        }
+    }
+}
+
+impl SerialType {
+    /// The number of bytes used to store a value of this type.
+    pub fn body_len(self) -> u64 {
+        match self {
+            Self::Null | Self::Zero | Self::One => 0,
+            Self::I8 => 1,
+            Self::I16 => 2,
+            Self::I24 => 3,
+            Self::I32 => 4,
+            Self::I48 => 6,
+            Self::I64 | Self::F64 => 8,
+            Self::Blob(n) | Self::Text(n) => n,
         }
     }
 }
 
 impl SerialValue {
-    pub fn consume(ty: SerialType, data: &mut ArcBufSlice) -> Self {
+    /// Stores an integer using the smallest serial type that can hold it.
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            0 => Self::Zero,
+            1 => Self::One,
+            -0x80..=0x7f => Self::I8(value as i8),
+            -0x8000..=0x7fff => Self::I16(I16::new(value as i16)),
+            -0x80_0000..=0x7f_ffff => Self::I24(I24::new(value as i32)),
+            -0x8000_0000..=0x7fff_ffff => Self::I32(I32::new(value as i32)),
+            -0x8000_0000_0000..=0x7fff_ffff_ffff => Self::I48(I48::new(value)),
+            _ => Self::I64(I64::new(value)),
+        }
+    }
+
+    /// Stores a float the way SQLite does. SQLite has no representation for
+    /// NaN and stores it as NULL, while infinities are kept as reals.
+    pub fn from_f64(value: f64) -> Self {
+        if value.is_nan() {
+            Self::Null
+        } else {
+            Self::F64(F64::new(value))
+        }
+    }
+
+    /// Converts the value as SQLite would when storing it in a column with
+    /// the given affinity. Numeric text becomes a number in numeric columns,
+    /// and integers become floats in `REAL` columns.
+    pub fn apply_affinity(self, affinity: Affinity) -> Self {
+        match (affinity, self) {
+            (Affinity::Integer | Affinity::Numeric | Affinity::Real, Self::Text(text)) => {
+                let trimmed = text.trim();
+                let looks_numeric = trimmed.bytes().any(|b| b.is_ascii_digit())
+                    && trimmed
+                        .bytes()
+                        .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b));
+                let number = if !looks_numeric {
+                    None
+                } else if let Ok(value) = trimmed.parse::<i64>() {
+                    Some(Self::from_i64(value))
+                } else {
+                    trimmed.parse::<f64>().ok().map(|value| {
+                        // Reals without a fractional part are stored as integers.
+                        if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+                            Self::from_i64(value as i64)
+                        } else {
+                            Self::from_f64(value)
+                        }
+                    })
+                };
+
+                match number {
+                    Some(number) if affinity == Affinity::Real => number.apply_affinity(affinity),
+                    Some(number) => number,
+                    None => Self::Text(text),
+                }
+            }
+            (Affinity::Real, value) => match value.as_i64() {
+                Some(int) => Self::from_f64(int as f64),
+                None => value,
+            },
+            (_, value) => value,
+        }
+    }
+
+    /// Returns the value if it is stored as an integer. Reals are not
+    /// converted.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::I8(value) => Some(*value as i64),
+            Self::I16(value) => Some(value.get() as i64),
+            Self::I24(value) => Some(value.get() as i64),
+            Self::I32(value) => Some(value.get() as i64),
+            Self::I48(value) => Some(value.get()),
+            Self::I64(value) => Some(value.get()),
+            Self::Zero => Some(0),
+            Self::One => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn serial_type(&self) -> SerialType {
+        match self {
+            Self::Null => SerialType::Null,
+            Self::I8(_) => SerialType::I8,
+            Self::I16(_) => SerialType::I16,
+            Self::I24(_) => SerialType::I24,
+            Self::I32(_) => SerialType::I32,
+            Self::I48(_) => SerialType::I48,
+            Self::I64(_) => SerialType::I64,
+            Self::F64(_) => SerialType::F64,
+            Self::Zero => SerialType::Zero,
+            Self::One => SerialType::One,
+            Self::Blob(value) => SerialType::Blob(value.len() as u64),
+            Self::Text(value) => SerialType::Text(value.len() as u64),
+        }
+    }
+
+    fn write_body(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Null | Self::Zero | Self::One => {}
+            Self::I8(value) => buf.push(*value as u8),
+            Self::I16(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::I24(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::I32(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::I48(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::I64(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::F64(value) => buf.extend_from_slice(value.as_bytes()),
+            Self::Blob(value) => buf.extend_from_slice(value),
+            Self::Text(value) => buf.extend_from_slice(value.as_bytes()),
+        }
+    }
+
+    pub fn consume(ty: SerialType, data: &mut ArcBufSlice) -> Result<Self> {
+        match ty {
+            SerialType::Text(n) => {
+                let text = String::from_utf8(data.consume_bytes(n as usize).to_vec())?;
+                Ok(Self::Text(text))
+            }
+            ty => Ok(Self::consume_lossy(ty, data)),
+        }
+    }
+
+    /// Like [`SerialValue::consume`], but replaces invalid UTF-8 in text values
+    /// with U+FFFD.
+    pub fn consume_lossy(ty: SerialType, data: &mut ArcBufSlice) -> Self {
         match ty {
             SerialType::Null => Self::Null,
             SerialType::I8 => Self::I8(data.consume()),
@@ -116,7 +441,7 @@ impl SerialValue {
             SerialType::One => Self::One,
             SerialType::Blob(n) => Self::Blob(data.consume_bytes(n as usize).to_vec()),
             SerialType::Text(n) => {
-                Self::Text(String::from_utf8(data.consume_bytes(n as usize).to_vec()).unwrap())
+                Self::Text(String::from_utf8_lossy(data.consume_bytes(n as usize)).into_owned())
             }
         }
     }
@@ -124,10 +449,51 @@ impl SerialValue {
 
 #[cfg(test)]
 mod tests {
-    use crate::physical::buf::ArcBuf;
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    use serde::{
+        de::{IgnoredAny, IntoDeserializer},
+        Deserialize,
+    };
+
+    use crate::{
+        physical::{buf::ArcBuf, db::DB},
+        schema::{sql, SchemaType, Table, WithRowId},
+    };
 
     use super::*;
 
+    /// Records the largest allocation made on each thread, so that tests can
+    /// check that a value was never copied.
+    struct LargestAllocation;
+
+    thread_local! {
+        static LARGEST: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for LargestAllocation {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = LARGEST.try_with(|largest| largest.set(largest.get().max(layout.size())));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: LargestAllocation = LargestAllocation;
+
+    fn largest_allocation(f: impl FnOnce()) -> usize {
+        LARGEST.with(|largest| largest.set(0));
+        f();
+        LARGEST.with(Cell::get)
+    }
+
     const EXAMPLE_RECORD: &[u8] = &[
         6, 23, 23, 23, 1, 117, 116, 97, 98, 108, 101, 101, 109, 112, 116, 121, 101, 109, 112, 116,
         121, 2, 67, 82, 69, 65, 84, 69, 32, 84, 65, 66, 76, 69, 32, 101, 109, 112, 116, 121, 32,
@@ -140,7 +506,7 @@ mod tests {
         let data: ArcBuf = EXAMPLE_RECORD.to_vec().into();
         let record = Record::from(ArcBufSlice::from(data));
 
-        let types = record.types().collect::<Vec<_>>();
+        let types = record.types().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(
             types,
             vec![
@@ -153,12 +519,85 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_truncated_header() {
+        // The header claims three bytes, but the last serial type is cut off.
+        let data: ArcBuf = vec![3, 1, 0x81].into();
+        let record = Record::from(ArcBufSlice::from(data));
+        let mut values = record.values();
+        assert!(values.next().unwrap().is_err());
+        assert!(values.next().is_none());
+
+        // The header length itself is cut off.
+        let data: ArcBuf = vec![0x81].into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(record.values().collect::<Result<Vec<_>>>().is_err());
+        assert!(record.project(&[0]).is_err());
+
+        // The header is longer than the record.
+        let data: ArcBuf = vec![10, 1].into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(record.types().collect::<Result<Vec<_>>>().is_err());
+
+        // A value body runs past the end of the record.
+        let data: ArcBuf = vec![2, 6, 1, 2].into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(record.values().collect::<Result<Vec<_>>>().is_err());
+
+        // A reserved serial type, after which values cannot be located.
+        let data: ArcBuf = vec![3, 10, 1, 7].into();
+        let record = Record::from(ArcBufSlice::from(data));
+        let mut types = record.types();
+        assert!(types.next().unwrap().is_err());
+        assert!(types.next().is_none());
+        assert!(record.values().collect::<Result<Vec<_>>>().is_err());
+    }
+
+    #[test]
+    fn test_try_parse() {
+        let record = Record::try_parse(EXAMPLE_RECORD).unwrap();
+        assert_eq!(record.values().count(), 5);
+        assert!(Record::try_parse(&[1]).unwrap().values().next().is_none());
+
+        let malformed: &[&[u8]] = &[
+            // Empty, so there is no header length.
+            &[],
+            // The header length is cut off.
+            &[0x81],
+            // The header is longer than the record.
+            &[10, 1],
+            // The header length is shorter than its own varint.
+            &[0, 1],
+            // The last serial type is cut off.
+            &[3, 1, 0x81],
+            // A value body runs past the end of the record.
+            &[2, 6, 1, 2],
+            // Reserved serial types.
+            &[2, 10],
+            &[2, 11],
+            // Text that is not UTF-8.
+            &[2, 15, 0xff],
+            // Bytes left over after the last value.
+            &[2, 1, 7, 8],
+        ];
+        for data in malformed {
+            assert!(Record::try_parse(data).is_err(), "{data:?}");
+        }
+
+        // More columns than SQLite allows, all NULL.
+        let mut data = Vec::new();
+        varint::write(40003, &mut data);
+        assert_eq!(data.len(), 3);
+        data.resize(40003, 0);
+        assert!(Record::try_parse(&data).is_err());
+    }
+
     #[test]
     fn test_read_columns() {
         let data: ArcBuf = EXAMPLE_RECORD.to_vec().into();
         let record = Record::from(ArcBufSlice::from(data));
 
-        let columns = record.into_values().collect::<Vec<_>>();
+        let columns = record.into_values().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(
             columns,
             vec![
@@ -172,4 +611,355 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_build() {
+        let data: ArcBuf = EXAMPLE_RECORD.to_vec().into();
+        let values = Record::from(ArcBufSlice::from(data))
+            .into_values()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(Record::build(&values), EXAMPLE_RECORD);
+
+        let values = vec![
+            SerialValue::Null,
+            SerialValue::I16(I16::new(-300)),
+            SerialValue::F64(F64::new(1.5)),
+            SerialValue::One,
+            SerialValue::Blob(vec![0xab; 200]),
+            SerialValue::Text("hello".to_owned()),
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(record.values().collect::<Result<Vec<_>>>().unwrap(), values);
+    }
+
+    #[test]
+    fn test_integer_boundaries() {
+        let cases = [
+            (0, SerialType::Zero),
+            (1, SerialType::One),
+            (2, SerialType::I8),
+            (-1, SerialType::I8),
+            (127, SerialType::I8),
+            (128, SerialType::I16),
+            (-128, SerialType::I8),
+            (-129, SerialType::I16),
+            (32767, SerialType::I16),
+            (32768, SerialType::I24),
+            (-32769, SerialType::I24),
+            (8388607, SerialType::I24),
+            (8388608, SerialType::I32),
+            (2147483647, SerialType::I32),
+            (2147483648, SerialType::I48),
+            (-2147483649, SerialType::I48),
+            (140737488355327, SerialType::I48),
+            (140737488355328, SerialType::I64),
+            (i64::MAX, SerialType::I64),
+            (i64::MIN, SerialType::I64),
+        ];
+        for (int, ty) in cases {
+            let value = SerialValue::from_i64(int);
+            assert_eq!(value.serial_type(), ty, "{int}");
+            assert_eq!(value.as_i64(), Some(int));
+
+            let data: ArcBuf = Record::build(&[value]).into();
+            let record = Record::from(ArcBufSlice::from(data));
+            let read = record.values().next().unwrap().unwrap();
+            assert_eq!(read.as_i64(), Some(int));
+        }
+
+        assert_eq!(SerialValue::from_f64(1.0).as_i64(), None);
+        assert_eq!(SerialValue::Null.as_i64(), None);
+    }
+
+    #[test]
+    fn test_read_bools() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Flags(bool, bool, bool, bool, Option<bool>);
+
+        let values = [
+            SerialValue::Zero,
+            SerialValue::One,
+            SerialValue::I8(-1),
+            SerialValue::I32(I32::new(1 << 20)),
+            SerialValue::Null,
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(
+            Flags::deserialize(record.into_deserializer()).unwrap(),
+            Flags(false, true, true, true, None)
+        );
+    }
+
+    #[test]
+    fn test_read_renamed_enums() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(rename_all = "UPPERCASE")]
+        enum Priority {
+            High,
+            Low,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Task(Priority, Option<Priority>, Option<Priority>);
+
+        let values = [
+            SerialValue::Text("HIGH".to_owned()),
+            SerialValue::Text("LOW".to_owned()),
+            SerialValue::Null,
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(
+            Task::deserialize(record.into_deserializer()).unwrap(),
+            Task(Priority::High, Some(Priority::Low), None)
+        );
+
+        let values = [SerialValue::Text("High".to_owned())];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(<(Priority,)>::deserialize(record.into_deserializer()).is_err());
+
+        let values = [SerialValue::I8(1)];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(<(Priority,)>::deserialize(record.into_deserializer()).is_err());
+    }
+
+    #[test]
+    fn test_read_empty_text_and_blob() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Row(String, Vec<u8>, Vec<u8>);
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct BorrowedRow<'a>(&'a str, &'a [u8], Vec<u8>);
+
+        let values = [
+            SerialValue::Text(String::new()),
+            SerialValue::Blob(vec![]),
+            SerialValue::Blob(vec![]),
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(
+            record.types().collect::<Result<Vec<_>>>().unwrap(),
+            [
+                SerialType::Text(0),
+                SerialType::Blob(0),
+                SerialType::Blob(0)
+            ]
+        );
+        assert_eq!(
+            Row::deserialize(record.clone().into_deserializer()).unwrap(),
+            Row(String::new(), vec![], vec![])
+        );
+        assert_eq!(
+            BorrowedRow::deserialize(record.borrowed_deserializer()).unwrap(),
+            BorrowedRow("", &[], vec![])
+        );
+
+        let values = [
+            SerialValue::Text("a".to_owned()),
+            SerialValue::Blob(vec![1, 2]),
+            SerialValue::Blob(vec![3]),
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(
+            Row::deserialize(record.clone().into_deserializer()).unwrap(),
+            Row("a".to_owned(), vec![1, 2], vec![3])
+        );
+        assert_eq!(
+            BorrowedRow::deserialize(record.borrowed_deserializer()).unwrap(),
+            BorrowedRow("a", &[1, 2], vec![3])
+        );
+    }
+
+    #[test]
+    fn test_read_borrowed() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Row<'a>(i64, &'a str, Option<&'a str>, &'a [u8], bool);
+
+        let values = [
+            SerialValue::I16(I16::new(300)),
+            SerialValue::Text("borrowed".to_owned()),
+            SerialValue::Null,
+            SerialValue::Blob(vec![1, 2, 3]),
+            SerialValue::One,
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data.clone()));
+        let row = Row::deserialize(record.borrowed_deserializer()).unwrap();
+        assert_eq!(row, Row(300, "borrowed", None, &[1, 2, 3], true));
+        // The text points into the record rather than a copy.
+        assert!(data.as_ptr_range().contains(&row.1.as_ptr()));
+
+        let values = [SerialValue::Text("too".to_owned()), SerialValue::Zero];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(<(&str,)>::deserialize(record.borrowed_deserializer()).is_err());
+    }
+
+    #[test]
+    fn test_read_with_affinities() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Row(i64, f64, f64, String, String);
+
+        let values = [
+            SerialValue::Text(" 42 ".to_owned()),
+            SerialValue::Text("1.5e1".to_owned()),
+            SerialValue::I8(3),
+            SerialValue::Text("007".to_owned()),
+            SerialValue::Text("inf".to_owned()),
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        let affinities = sql::parse_create_table(
+            "CREATE TABLE t (a INTEGER, b NUMERIC, c REAL, d TEXT, e NUMERIC)",
+        )
+        .unwrap()
+        .affinities();
+
+        assert!(Row::deserialize(record.clone().into_deserializer()).is_err());
+        assert_eq!(
+            Row::deserialize(record.into_deserializer().with_affinities(affinities)).unwrap(),
+            Row(42, 15.0, 3.0, "007".to_owned(), "inf".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_read_f32() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Floats(f32, f32, f32, Option<f32>);
+
+        let values = [
+            SerialValue::F64(F64::new(0.1)),
+            SerialValue::F64(F64::new(1e40)),
+            SerialValue::I32(I32::new(16_777_217)),
+            SerialValue::Null,
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert_eq!(
+            Floats::deserialize(record.into_deserializer()).unwrap(),
+            Floats(0.1, f32::INFINITY, 16_777_216.0, None)
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // A single text column of length 2 holding invalid UTF-8.
+        let data: ArcBuf = vec![2, 17, 0xff, 0xfe].into();
+        let record = Record::from(ArcBufSlice::from(data));
+
+        let err = <(String,)>::deserialize(record.clone().into_deserializer()).unwrap_err();
+        assert!(err.to_string().contains("utf-8"), "{err}");
+
+        let columns = record.values().lossy().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(
+            columns,
+            vec![SerialValue::Text("\u{fffd}\u{fffd}".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_project() {
+        let mut data = Record::build(&[
+            SerialValue::I8(1),
+            SerialValue::Blob(vec![0; 100_000]),
+            SerialValue::Text("ab".to_owned()),
+            SerialValue::F64(F64::new(2.5)),
+        ]);
+        // Corrupt the text column so that decoding it would fail.
+        let text_start = data.len() - 10;
+        data[text_start] = 0xff;
+
+        let data: ArcBuf = data.into();
+        let record = Record::from(ArcBufSlice::from(data));
+        assert!(record.values().collect::<Result<Vec<_>>>().is_err());
+
+        let projected = record.project(&[3, 0, 3, 7]).unwrap();
+        assert_eq!(
+            projected,
+            vec![
+                SerialValue::F64(F64::new(2.5)),
+                SerialValue::I8(1),
+                SerialValue::F64(F64::new(2.5)),
+                SerialValue::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_skips_ignored_columns() {
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "attachments")]
+        struct Attachment {
+            #[table(row_id)]
+            id: i64,
+            name: String,
+            _data: IgnoredAny,
+        }
+
+        #[derive(Debug, Deserialize, Table)]
+        #[table(name = "attachments")]
+        struct AttachmentWithData {
+            #[table(row_id)]
+            id: i64,
+            _name: String,
+            data: Vec<u8>,
+        }
+
+        let db = DB::open("examples/blobs.db").unwrap();
+        let attachments = db.table::<Attachment>().unwrap();
+        // Read every page first, so that reading pages is not counted.
+        assert_eq!(attachments.row_count().unwrap(), 2);
+        assert_eq!(attachments.iter().unwrap().count(), 2);
+
+        // Each blob is 15000 bytes.
+        let mut rows = Vec::new();
+        let largest = largest_allocation(|| rows = attachments.get_vec(..).unwrap());
+        assert!(largest < 15000, "allocated {largest} bytes");
+        let names = rows
+            .iter()
+            .map(|row| (row.id, row.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [(1, "a.bin"), (2, "b.bin")]);
+
+        let mut rows = Vec::new();
+        let largest = largest_allocation(|| {
+            rows = db
+                .table::<AttachmentWithData>()
+                .unwrap()
+                .get_vec(..)
+                .unwrap()
+        });
+        assert!(largest >= 15000, "allocated {largest} bytes");
+        assert_eq!(rows[1].id, 2);
+        assert_eq!(rows[1].data, [0; 15000]);
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        assert_eq!(SerialValue::from_f64(f64::NAN), SerialValue::Null);
+
+        let values = [
+            SerialValue::from_f64(f64::INFINITY),
+            SerialValue::from_f64(f64::NEG_INFINITY),
+            SerialValue::from_f64(f64::NAN),
+        ];
+        let data: ArcBuf = Record::build(&values).into();
+        let record = Record::from(ArcBufSlice::from(data));
+        let columns = record.values().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                SerialValue::F64(F64::new(f64::INFINITY)),
+                SerialValue::F64(F64::new(f64::NEG_INFINITY)),
+                SerialValue::Null,
+            ]
+        );
+    }
 }
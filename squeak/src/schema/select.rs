@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+
+use crate::physical::{btree::iter::BTreeTableEntries, db::DB};
+
+use super::{
+    record::{Record, SerialValue},
+    sql::{self, ParsedTable},
+    Schema, SchemaType, Table,
+};
+
+// The schema table is not described in itself.
+const SCHEMA_SQL: &str =
+    "CREATE TABLE sqlite_schema (type text, name text, tbl_name text, rootpage integer, sql text)";
+
+/// A row read without a Rust type to deserialize it into, whose values can be
+/// looked up by column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedRow {
+    row_id: i64,
+    columns: Arc<[String]>,
+    values: Vec<SerialValue>,
+}
+
+/// The rows of a table, in row id order. See [`DB::select`].
+pub struct NamedRowIter {
+    entries: BTreeTableEntries,
+    columns: Arc<[String]>,
+    row_id_column: Option<usize>,
+}
+
+impl NamedRow {
+    pub fn row_id(&self) -> i64 {
+        self.row_id
+    }
+
+    /// Returns the value of the named column. Returns `None` if there is no
+    /// such column, or if the record was written before the column was added
+    /// and so does not store it.
+    pub fn get(&self, column_name: &str) -> Option<&SerialValue> {
+        let index = self.columns.iter().position(|name| name == column_name)?;
+        self.values.get(index)
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[SerialValue] {
+        &self.values
+    }
+}
+
+impl Iterator for NamedRowIter {
+    type Item = Result<NamedRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (row_id, data) = match self.entries.next()? {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err)),
+        };
+        let values = Record::from(data).values().collect::<Result<Vec<_>>>();
+        Some(values.map(|mut values| {
+            if let Some(value @ SerialValue::Null) =
+                self.row_id_column.and_then(|column| values.get_mut(column))
+            {
+                *value = SerialValue::from_i64(row_id);
+            }
+            NamedRow {
+                row_id,
+                columns: self.columns.clone(),
+                values,
+            }
+        }))
+    }
+}
+
+impl DB {
+    /// Reads every row of the named table, with column names taken from its
+    /// `CREATE TABLE` statement. The row id is read in place of the NULL
+    /// stored in an `INTEGER PRIMARY KEY` column.
+    pub fn select(&self, table: &str) -> Result<NamedRowIter> {
+        let (rootpage, parsed) = self.find_table(table)?;
+        if parsed.without_row_id {
+            return Err(anyhow!("cannot select from WITHOUT ROWID table {table}"));
+        }
+
+        Ok(NamedRowIter {
+            entries: self
+                .btree_page(rootpage)?
+                .into_table_entries_range(None..None)?,
+            row_id_column: parsed.row_id_column(),
+            columns: parsed
+                .columns
+                .into_iter()
+                .map(|column| column.name)
+                .collect(),
+        })
+    }
+
+    /// Reads every row of the named table into `P` by column name, so that
+    /// maps, structs with fields in a different order than the columns, and
+    /// `#[serde(flatten)]` fields can be read. Values are converted according
+    /// to the affinity of their column, columns added after a row was
+    /// written take their `DEFAULT`, and the row id is read in place of the
+    /// NULL stored in an `INTEGER PRIMARY KEY` column.
+    pub fn select_into<P: DeserializeOwned>(
+        &self,
+        table: &str,
+    ) -> Result<impl Iterator<Item = Result<P>>> {
+        let (rootpage, parsed) = self.find_table(table)?;
+        if parsed.without_row_id {
+            return Err(anyhow!("cannot select from WITHOUT ROWID table {table}"));
+        }
+
+        let affinities = parsed.affinities();
+        let defaults = Arc::from(parsed.defaults());
+        let row_id_column = parsed.row_id_column();
+        let columns = parsed
+            .columns
+            .into_iter()
+            .map(|column| column.name)
+            .collect::<Vec<_>>();
+        let entries = self
+            .btree_page(rootpage)?
+            .into_table_entries_range(None..None)?;
+        Ok(entries.map(move |entry| {
+            let (row_id, data) = entry?;
+            let mut deserializer = Record::from(data)
+                .into_map_deserializer(&columns)
+                .with_affinities(affinities.clone())
+                .with_defaults(Arc::clone(&defaults));
+            if let Some(column) = row_id_column {
+                deserializer = deserializer.with_row_id(column, row_id);
+            }
+            Ok(P::deserialize(deserializer)?)
+        }))
+    }
+
+    pub(super) fn find_table(&self, table: &str) -> Result<(u32, ParsedTable)> {
+        if table == Schema::NAME {
+            return Ok((1, sql::parse_create_table(SCHEMA_SQL)?));
+        }
+        for schema in self.table::<Schema>()?.iter()? {
+            let schema = schema?;
+            if schema.type_ == SchemaType::Table && schema.name == table {
+                return Ok((schema.rootpage, schema.parse_create_table()?));
+            }
+        }
+        Err(anyhow!("Table {table} not found in schema"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn test_select_schema() {
+        let db = DB::open("examples/empty.db").unwrap();
+        let rows = db
+            .select("sqlite_schema")
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].get("name"),
+            Some(&SerialValue::Text("empty".to_owned()))
+        );
+        assert_eq!(
+            rows[0].get("rootpage").and_then(SerialValue::as_i64),
+            Some(2)
+        );
+        assert_eq!(rows[0].get("missing"), None);
+    }
+
+    #[test]
+    fn test_select_into() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Square {
+            square: i64,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Number {
+            name: String,
+            #[serde(flatten)]
+            square: Square,
+            id: i64,
+        }
+
+        let db = DB::open("examples/numbers.db").unwrap();
+        let row = db
+            .select_into::<Number>("numbers")
+            .unwrap()
+            .nth(11)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            row,
+            Number {
+                name: "number 12".to_owned(),
+                square: Square { square: 144 },
+                id: 12,
+            }
+        );
+
+        let row = db
+            .select_into::<HashMap<String, serde_json::Value>>("numbers")
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(row.len(), 3);
+        assert_eq!(row["id"], 1);
+        assert_eq!(row["name"], "number 1");
+    }
+
+    #[test]
+    fn test_select() {
+        let db = DB::open("examples/numbers.db").unwrap();
+        let row = db.select("numbers").unwrap().nth(11).unwrap().unwrap();
+        assert_eq!(row.columns(), ["id", "square", "name"]);
+        assert_eq!(row.get("id").and_then(SerialValue::as_i64), Some(12));
+        assert_eq!(row.get("square").and_then(SerialValue::as_i64), Some(144));
+        assert_eq!(
+            row.get("name"),
+            Some(&SerialValue::Text("number 12".to_owned()))
+        );
+
+        assert!(db.select("missing").is_err());
+        let db = DB::open("examples/without_rowid.db").unwrap();
+        assert!(db.select("key_value").is_err());
+    }
+}
@@ -1,27 +1,488 @@
+use std::{slice, sync::Arc};
+
 use serde::{
     de::{
         self,
-        value::{Error, SeqDeserializer},
-        IntoDeserializer,
+        value::{BorrowedStrDeserializer, Error, SeqDeserializer},
+        DeserializeSeed, Error as _, IntoDeserializer, MapAccess, SeqAccess,
     },
-    Deserializer,
+    forward_to_deserialize_any, Deserializer,
 };
 
-use crate::schema::record::{iter::SerialValueIterator, Record, SerialValue};
+use crate::{
+    physical::buf::ArcBufSlice,
+    schema::{
+        record::{
+            iter::{BorrowedValueIterator, SerialValueIterator},
+            Record, SerialType, SerialValue, ValueRef,
+        },
+        sql::Affinity,
+    },
+};
 
 pub mod row_id {
     use serde::{Deserialize, Deserializer};
 
-    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
         Option::deserialize(deserializer).map(|o| o.unwrap_or(0))
     }
 }
 
+pub struct RecordDeserializer {
+    values: SerialValueIterator,
+    count: usize,
+    // The number of fields of the struct or tuple being read, if known
+    len: usize,
+    affinities: Vec<Affinity>,
+    defaults: Arc<[SerialValue]>,
+    row_id: Option<(usize, i64)>,
+    ignore_trailing: bool,
+}
+
+impl RecordDeserializer {
+    /// Converts each value according to the affinity of its column before
+    /// deserializing it, so that numeric text can be read into number fields.
+    /// See [`SerialValue::apply_affinity`].
+    pub fn with_affinities(mut self, affinities: Vec<Affinity>) -> Self {
+        self.affinities = affinities;
+        self
+    }
+
+    /// Reads the given value of each column in place of values missing from
+    /// the end of the record, as SQLite does for columns added by `ALTER
+    /// TABLE ADD COLUMN`. Missing columns without a default are read as NULL.
+    /// See [`ParsedTable::defaults`](super::sql::ParsedTable::defaults).
+    pub fn with_defaults(mut self, defaults: Arc<[SerialValue]>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Reads `row_id` in place of a NULL value in the given column, which is
+    /// how SQLite stores `INTEGER PRIMARY KEY` columns.
+    pub fn with_row_id(mut self, column: usize, row_id: i64) -> Self {
+        self.row_id = Some((column, row_id));
+        self
+    }
+
+    /// Ignores values after the last field of the struct or tuple being read,
+    /// such as the row id at the end of an index entry, instead of reporting
+    /// them as an error.
+    pub fn ignoring_trailing(mut self) -> Self {
+        self.ignore_trailing = true;
+        self
+    }
+}
+
 impl<'de> IntoDeserializer<'de> for Record {
-    type Deserializer = SeqDeserializer<SerialValueIterator, Error>;
+    type Deserializer = RecordDeserializer;
 
     fn into_deserializer(self) -> Self::Deserializer {
-        SeqDeserializer::new(self.into_values())
+        RecordDeserializer {
+            values: self.into_values(),
+            count: 0,
+            len: 0,
+            affinities: Vec::new(),
+            defaults: Arc::default(),
+            row_id: None,
+            ignore_trailing: false,
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for RecordDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let value = visitor.visit_seq(&mut self)?;
+        if self.ignore_trailing {
+            return Ok(value);
+        }
+
+        let remaining = self.values.count();
+        if remaining > 0 {
+            let expected = format!("{} elements in sequence", self.count);
+            return Err(Error::invalid_length(
+                self.count + remaining,
+                &expected.as_str(),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = len;
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        mut self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = len;
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = fields.len();
+        self.deserialize_any(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq map enum
+        identifier ignored_any
+    }
+}
+
+/// The value of a column missing from the end of a record.
+fn missing_value(defaults: &[SerialValue], column: usize) -> SerialValue {
+    defaults.get(column).cloned().unwrap_or(SerialValue::Null)
+}
+
+impl<'de> SeqAccess<'de> for RecordDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = match self.values.next_raw() {
+            Some(Ok((ty, body))) => LazyValue::Raw(ty, body),
+            Some(Err(err)) => return Err(Error::custom(err)),
+            // Records written before ALTER TABLE ADD COLUMN lack the new
+            // columns, which take their DEFAULT.
+            None if self.count < self.len => {
+                LazyValue::Decoded(missing_value(&self.defaults, self.count))
+            }
+            None => return Ok(None),
+        };
+        let value = LazyValueDeserializer {
+            value,
+            row_id: self
+                .row_id
+                .and_then(|(column, row_id)| (column == self.count).then_some(row_id)),
+            affinity: self.affinities.get(self.count).copied(),
+        };
+        self.count += 1;
+        seed.deserialize(value).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.values.size_hint().1
+    }
+}
+
+/// A value of a record that is only decoded once the type being read asks for
+/// it, so that text and blobs read into `IgnoredAny` are never copied.
+struct LazyValueDeserializer {
+    value: LazyValue,
+    // Read in place of NULL
+    row_id: Option<i64>,
+    affinity: Option<Affinity>,
+}
+
+enum LazyValue {
+    Raw(SerialType, ArcBufSlice),
+    Decoded(SerialValue),
+}
+
+impl LazyValueDeserializer {
+    fn decode(self) -> Result<SerialValue, Error> {
+        let mut value = match self.value {
+            LazyValue::Raw(ty, mut body) => {
+                SerialValue::consume(ty, &mut body).map_err(Error::custom)?
+            }
+            LazyValue::Decoded(value) => value,
+        };
+        if let (Some(row_id), SerialValue::Null) = (self.row_id, &value) {
+            value = SerialValue::from_i64(row_id);
+        }
+        if let Some(affinity) = self.affinity {
+            value = value.apply_affinity(affinity);
+        }
+        Ok(value)
+    }
+}
+
+/// Implements each method by decoding the value and passing it on.
+macro_rules! forward_to_decoded {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.decode()?.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for LazyValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_decoded! {
+        deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16()
+        deserialize_i32() deserialize_i64() deserialize_i128() deserialize_u8()
+        deserialize_u16() deserialize_u32() deserialize_u64() deserialize_u128()
+        deserialize_f32() deserialize_f64() deserialize_char() deserialize_str()
+        deserialize_string() deserialize_bytes() deserialize_byte_buf()
+        deserialize_option() deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_seq() deserialize_tuple(len: usize)
+        deserialize_tuple_struct(name: &'static str, len: usize)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+    }
+}
+
+/// Deserializes a record into types that borrow from it, such as structs
+/// with `&str` or `&[u8]` fields.
+pub struct BorrowedRecordDeserializer<'a> {
+    values: BorrowedValueIterator<'a>,
+    count: usize,
+    len: usize,
+    defaults: Arc<[SerialValue]>,
+}
+
+impl Record {
+    /// Deserializes without copying text and blobs, so that the result can
+    /// borrow from the record.
+    pub fn borrowed_deserializer(&self) -> BorrowedRecordDeserializer<'_> {
+        BorrowedRecordDeserializer {
+            values: self.borrowed_values(),
+            count: 0,
+            len: 0,
+            defaults: Arc::default(),
+        }
+    }
+}
+
+impl<'a> BorrowedRecordDeserializer<'a> {
+    /// Like [`RecordDeserializer::with_defaults`].
+    pub fn with_defaults(mut self, defaults: Arc<[SerialValue]>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for BorrowedRecordDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let value = visitor.visit_seq(&mut self)?;
+
+        let remaining = self.values.count();
+        if remaining > 0 {
+            let expected = format!("{} elements in sequence", self.count);
+            return Err(Error::invalid_length(
+                self.count + remaining,
+                &expected.as_str(),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = len;
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        mut self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = len;
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.len = fields.len();
+        self.deserialize_any(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq map enum
+        identifier ignored_any
+    }
+}
+
+impl<'de> SeqAccess<'de> for BorrowedRecordDeserializer<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = match self.values.next() {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => return Err(Error::custom(err)),
+            None if self.count < self.len => {
+                ValueRef::Owned(missing_value(&self.defaults, self.count))
+            }
+            None => return Ok(None),
+        };
+        self.count += 1;
+        seed.deserialize(value).map(Some)
+    }
+}
+
+/// Deserializes a record as a map from column name to value.
+pub struct RecordMapDeserializer<'a, S> {
+    columns: slice::Iter<'a, S>,
+    values: SerialValueIterator,
+    count: usize,
+    affinities: Vec<Affinity>,
+    defaults: Arc<[SerialValue]>,
+    row_id: Option<(usize, i64)>,
+}
+
+impl Record {
+    /// Pairs each value with the corresponding column name, so the record can
+    /// be deserialized into a map or a `serde_json::Value`. Columns missing
+    /// from the end of the record are read as NULL, unless given a default
+    /// with [`RecordMapDeserializer::with_defaults`].
+    pub fn into_map_deserializer<S: AsRef<str>>(
+        self,
+        columns: &[S],
+    ) -> RecordMapDeserializer<'_, S> {
+        RecordMapDeserializer {
+            columns: columns.iter(),
+            values: self.into_values(),
+            count: 0,
+            affinities: Vec::new(),
+            defaults: Arc::default(),
+            row_id: None,
+        }
+    }
+}
+
+impl<'a, S> RecordMapDeserializer<'a, S> {
+    /// Like [`RecordDeserializer::with_affinities`].
+    pub fn with_affinities(mut self, affinities: Vec<Affinity>) -> Self {
+        self.affinities = affinities;
+        self
+    }
+
+    /// Like [`RecordDeserializer::with_defaults`].
+    pub fn with_defaults(mut self, defaults: Arc<[SerialValue]>) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Like [`RecordDeserializer::with_row_id`].
+    pub fn with_row_id(mut self, column: usize, row_id: i64) -> Self {
+        self.row_id = Some((column, row_id));
+        self
+    }
+}
+
+impl<'de, 'a, S: AsRef<str>> Deserializer<'de> for RecordMapDeserializer<'a, S> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'a, S: AsRef<str>> MapAccess<'de> for RecordMapDeserializer<'a, S> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.columns.next() {
+            Some(column) => seed
+                .deserialize(column.as_ref().into_deserializer())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let mut value = match self.values.next() {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => return Err(Error::custom(err)),
+            None => missing_value(&self.defaults, self.count),
+        };
+        if let (Some((column, row_id)), SerialValue::Null) = (self.row_id, &value) {
+            if column == self.count {
+                value = SerialValue::from_i64(row_id);
+            }
+        }
+        if let Some(&affinity) = self.affinities.get(self.count) {
+            value = value.apply_affinity(affinity);
+        }
+        self.count += 1;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len())
     }
 }
 
@@ -60,7 +521,18 @@ impl<'de> Deserializer<'de> for SerialValue {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        // SQLite stores booleans as integers, and treats any non-zero value as true.
+        match self {
+            SerialValue::Zero => visitor.visit_bool(false),
+            SerialValue::One => visitor.visit_bool(true),
+            SerialValue::I8(value) => visitor.visit_bool(value != 0),
+            SerialValue::I16(value) => visitor.visit_bool(value.get() != 0),
+            SerialValue::I24(value) => visitor.visit_bool(value.get() != 0),
+            SerialValue::I32(value) => visitor.visit_bool(value.get() != 0),
+            SerialValue::I48(value) => visitor.visit_bool(value.get() != 0),
+            SerialValue::I64(value) => visitor.visit_bool(value.get() != 0),
+            _ => self.deserialize_any(visitor),
+        }
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -123,7 +595,15 @@ impl<'de> Deserializer<'de> for SerialValue {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        // Values are rounded to the nearest f32, so large values become
+        // infinite and integers above 2^24 may lose precision.
+        match self {
+            SerialValue::F64(value) => visitor.visit_f32(value.get() as f32),
+            _ => match self.as_i64() {
+                Some(value) => visitor.visit_f32(value as f32),
+                None => self.deserialize_any(visitor),
+            },
+        }
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -212,7 +692,11 @@ impl<'de> Deserializer<'de> for SerialValue {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        // Types like `Vec<u8>` ask for a sequence rather than bytes.
+        match self {
+            SerialValue::Blob(value) => visitor.visit_seq(SeqDeserializer::new(value.into_iter())),
+            _ => self.deserialize_any(visitor),
+        }
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
@@ -253,9 +737,11 @@ impl<'de> Deserializer<'de> for SerialValue {
         self.deserialize_any(visitor)
     }
 
+    /// Unit variants are stored as text. The variant name is matched by serde,
+    /// so attributes such as `rename_all` on the enum are honoured.
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
@@ -265,7 +751,9 @@ impl<'de> Deserializer<'de> for SerialValue {
         if let Self::Text(text) = self {
             visitor.visit_enum(text.into_deserializer())
         } else {
-            todo!()
+            Err(Error::custom(format!(
+                "expected text for enum {name}, found {self:?}"
+            )))
         }
     }
 
@@ -283,3 +771,94 @@ impl<'de> Deserializer<'de> for SerialValue {
         self.deserialize_any(visitor)
     }
 }
+
+impl<'de> IntoDeserializer<'de> for ValueRef<'de> {
+    type Deserializer = ValueRef<'de>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// Text and blobs are visited as borrowed; other values are deserialized
+/// like a [`SerialValue`].
+impl<'de> Deserializer<'de> for ValueRef<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            ValueRef::Text(text) => visitor.visit_borrowed_str(text),
+            ValueRef::Blob(blob) => visitor.visit_borrowed_bytes(blob),
+            ValueRef::Owned(value) => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            ValueRef::Owned(value) => value.deserialize_bool(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            ValueRef::Owned(value) => value.deserialize_f32(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let ValueRef::Owned(SerialValue::Null) = self {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            ValueRef::Blob(blob) => visitor.visit_seq(SeqDeserializer::new(blob.iter().copied())),
+            ValueRef::Owned(value) => value.deserialize_seq(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            ValueRef::Text(text) => visitor.visit_enum(BorrowedStrDeserializer::new(text)),
+            ValueRef::Blob(blob) => {
+                SerialValue::Blob(blob.to_vec()).deserialize_enum(name, variants, visitor)
+            }
+            ValueRef::Owned(value) => value.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
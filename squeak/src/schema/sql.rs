@@ -0,0 +1,609 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+
+use super::record::SerialValue;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedTable {
+    pub name: String,
+    pub columns: Vec<ParsedColumn>,
+    pub without_row_id: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedColumn {
+    pub name: String,
+    pub declared_type: Option<String>,
+    pub not_null: bool,
+    pub primary_key: bool,
+    /// The default value expression, as written in the SQL.
+    pub default: Option<String>,
+}
+
+/// The type affinity of a column, which determines how values stored in it
+/// are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Text,
+    Numeric,
+    Integer,
+    Real,
+    Blob,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Word,
+    Quoted,
+    String,
+    Punct(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "CONSTRAINT",
+    "PRIMARY",
+    "NOT",
+    "NULL",
+    "UNIQUE",
+    "CHECK",
+    "DEFAULT",
+    "COLLATE",
+    "REFERENCES",
+    "GENERATED",
+    "AS",
+];
+
+const TABLE_CONSTRAINT_KEYWORDS: &[&str] = &["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"];
+
+/// Parses the subset of `CREATE TABLE` statements needed to recover column
+/// definitions. Column constraints other than those in [`ParsedColumn`] are
+/// skipped.
+pub fn parse_create_table(sql: &str) -> Result<ParsedTable> {
+    let tokens = tokenize(sql)?;
+    let mut parser = Parser {
+        sql,
+        tokens: &tokens,
+        pos: 0,
+    };
+
+    parser.expect_keyword("CREATE")?;
+    if parser.peek_keyword("TEMP") || parser.peek_keyword("TEMPORARY") {
+        parser.pos += 1;
+    }
+    parser.expect_keyword("TABLE")?;
+    if parser.peek_keyword("IF") {
+        parser.expect_keyword("IF")?;
+        parser.expect_keyword("NOT")?;
+        parser.expect_keyword("EXISTS")?;
+    }
+
+    let mut name = parser.identifier()?;
+    if parser.peek_punct('.') {
+        parser.pos += 1;
+        name = parser.identifier()?;
+    }
+
+    parser.expect_punct('(')?;
+    let mut columns = Vec::new();
+    let mut primary_key_columns = Vec::new();
+    loop {
+        let definition = parser.until_top_level_comma()?;
+        let first = definition
+            .first()
+            .ok_or_else(|| anyhow!("empty column definition"))?;
+        if is_keyword(sql, first, TABLE_CONSTRAINT_KEYWORDS) {
+            primary_key_columns.extend(parse_table_constraint(sql, definition)?);
+        } else {
+            columns.push(parse_column(sql, definition)?);
+        }
+
+        if parser.peek_punct(',') {
+            parser.pos += 1;
+        } else {
+            parser.expect_punct(')')?;
+            break;
+        }
+    }
+
+    for column in &mut columns {
+        if primary_key_columns.contains(&column.name) {
+            column.primary_key = true;
+        }
+    }
+
+    let mut without_row_id = false;
+    while parser.pos < tokens.len() {
+        if parser.peek_keyword("WITHOUT") {
+            parser.expect_keyword("WITHOUT")?;
+            parser.expect_keyword("ROWID")?;
+            without_row_id = true;
+        } else {
+            parser.pos += 1;
+        }
+    }
+
+    Ok(ParsedTable {
+        name,
+        columns,
+        without_row_id,
+    })
+}
+
+impl ParsedTable {
+    pub fn affinities(&self) -> Vec<Affinity> {
+        self.columns.iter().map(ParsedColumn::affinity).collect()
+    }
+
+    /// The column that is an alias for the row id, which is the only primary
+    /// key column when it is declared as `INTEGER`. SQLite stores NULL in
+    /// that column.
+    pub fn row_id_column(&self) -> Option<usize> {
+        if self.without_row_id {
+            return None;
+        }
+        let mut primary_key = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.primary_key);
+        let (index, column) = primary_key.next()?;
+        if primary_key.next().is_some() {
+            return None;
+        }
+        column
+            .declared_type
+            .as_deref()
+            .is_some_and(|ty| ty.eq_ignore_ascii_case("integer"))
+            .then_some(index)
+    }
+
+    /// The value read for each column when a record is too short to store
+    /// it. See [`ParsedColumn::default_value`].
+    pub fn defaults(&self) -> Vec<SerialValue> {
+        self.columns
+            .iter()
+            .map(|column| column.default_value().unwrap_or(SerialValue::Null))
+            .collect()
+    }
+}
+
+impl ParsedColumn {
+    /// Determines the affinity from the declared type, using the rules in
+    /// section 3.1 of <https://www.sqlite.org/datatype3.html>.
+    pub fn affinity(&self) -> Affinity {
+        let Some(declared_type) = &self.declared_type else {
+            return Affinity::Blob;
+        };
+        let declared_type = declared_type.to_ascii_uppercase();
+        let contains = |pattern| declared_type.contains(pattern);
+
+        if contains("INT") {
+            Affinity::Integer
+        } else if contains("CHAR") || contains("CLOB") || contains("TEXT") {
+            Affinity::Text
+        } else if contains("BLOB") {
+            Affinity::Blob
+        } else if contains("REAL") || contains("FLOA") || contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Evaluates the `DEFAULT` clause, converted by the column's affinity.
+    /// Returns `None` if there is no default or if it is not a literal, such
+    /// as an expression in parentheses or a blob.
+    pub fn default_value(&self) -> Option<SerialValue> {
+        let default = self.default.as_deref()?;
+        let value = if default.eq_ignore_ascii_case("NULL") {
+            SerialValue::Null
+        } else if default.eq_ignore_ascii_case("TRUE") {
+            SerialValue::One
+        } else if default.eq_ignore_ascii_case("FALSE") {
+            SerialValue::Zero
+        } else if let Some(text) = default
+            .strip_prefix('\'')
+            .and_then(|text| text.strip_suffix('\''))
+        {
+            SerialValue::Text(text.replace("''", "'"))
+        } else {
+            // A sign may be separated from the number by whitespace.
+            let number = default.split_whitespace().collect::<String>();
+            if let Ok(integer) = number.parse() {
+                SerialValue::from_i64(integer)
+            } else if number
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+            {
+                SerialValue::from_f64(number.parse().ok()?)
+            } else {
+                return None;
+            }
+        };
+        Some(value.apply_affinity(self.affinity()))
+    }
+}
+
+fn parse_column(sql: &str, tokens: &[Token]) -> Result<ParsedColumn> {
+    let name = identifier(sql, &tokens[0])?;
+
+    let mut pos = 1;
+    while pos < tokens.len() && !is_keyword(sql, &tokens[pos], CONSTRAINT_KEYWORDS) {
+        pos = skip_group(tokens, pos);
+    }
+    let declared_type =
+        (pos > 1).then(|| sql[tokens[1].span.start..tokens[pos - 1].span.end].to_owned());
+
+    let mut column = ParsedColumn {
+        name,
+        declared_type,
+        not_null: false,
+        primary_key: false,
+        default: None,
+    };
+
+    while pos < tokens.len() {
+        let token = &tokens[pos];
+        pos += 1;
+        if token.kind != TokenKind::Word {
+            continue;
+        }
+
+        match sql[token.span.clone()].to_ascii_uppercase().as_str() {
+            "PRIMARY" => column.primary_key = true,
+            "NOT"
+                if tokens
+                    .get(pos)
+                    .is_some_and(|t| is_keyword(sql, t, &["NULL"])) =>
+            {
+                column.not_null = true;
+                pos += 1;
+            }
+            "DEFAULT" => {
+                let start = pos;
+                if tokens
+                    .get(pos)
+                    .is_some_and(|t| matches!(t.kind, TokenKind::Punct('+' | '-')))
+                {
+                    pos += 1;
+                }
+                if pos >= tokens.len() {
+                    return Err(anyhow!("missing default value for {}", column.name));
+                }
+                pos = skip_group(tokens, pos);
+                column.default =
+                    Some(sql[tokens[start].span.start..tokens[pos - 1].span.end].to_owned());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(column)
+}
+
+/// Returns the columns named by a `PRIMARY KEY (...)` table constraint.
+fn parse_table_constraint(sql: &str, tokens: &[Token]) -> Result<Vec<String>> {
+    let Some(primary) = tokens
+        .iter()
+        .position(|token| is_keyword(sql, token, &["PRIMARY"]))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut columns = Vec::new();
+    let mut depth = 0;
+    let mut expect_name = true;
+    for token in &tokens[primary..] {
+        match token.kind {
+            TokenKind::Punct('(') => depth += 1,
+            TokenKind::Punct(')') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            TokenKind::Punct(',') if depth == 1 => expect_name = true,
+            // Only the first token of each indexed column is its name, which
+            // skips COLLATE and ASC/DESC.
+            _ if depth == 1 && expect_name => {
+                columns.push(identifier(sql, token)?);
+                expect_name = false;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(columns)
+}
+
+/// Returns the index after the token at `pos`, or after the matching
+/// parenthesis if the token opens a group.
+fn skip_group(tokens: &[Token], mut pos: usize) -> usize {
+    let mut depth = 0;
+    loop {
+        match tokens[pos].kind {
+            TokenKind::Punct('(') => depth += 1,
+            TokenKind::Punct(')') => depth -= 1,
+            _ => {}
+        }
+        pos += 1;
+        if depth <= 0 || pos >= tokens.len() {
+            return pos;
+        }
+    }
+}
+
+fn is_keyword(sql: &str, token: &Token, keywords: &[&str]) -> bool {
+    token.kind == TokenKind::Word
+        && keywords
+            .iter()
+            .any(|keyword| sql[token.span.clone()].eq_ignore_ascii_case(keyword))
+}
+
+fn identifier(sql: &str, token: &Token) -> Result<String> {
+    let text = &sql[token.span.clone()];
+    match token.kind {
+        TokenKind::Word => Ok(text.to_owned()),
+        TokenKind::Quoted | TokenKind::String => {
+            let quote = text.chars().next().unwrap();
+            let inner = &text[1..text.len() - 1];
+            if quote == '[' {
+                Ok(inner.to_owned())
+            } else {
+                Ok(inner.replace(&format!("{quote}{quote}"), &quote.to_string()))
+            }
+        }
+        TokenKind::Punct(c) => Err(anyhow!("expected identifier, found {c:?}")),
+    }
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|token| is_keyword(self.sql, token, &[keyword]))
+    }
+
+    fn peek_punct(&self, c: char) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|token| token.kind == TokenKind::Punct(c))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if !self.peek_keyword(keyword) {
+            return Err(anyhow!("expected {keyword}"));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<()> {
+        if !self.peek_punct(c) {
+            return Err(anyhow!("expected {c:?}"));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| anyhow!("expected identifier"))?;
+        self.pos += 1;
+        identifier(self.sql, token)
+    }
+
+    /// Consumes tokens up to the next comma or closing parenthesis at the
+    /// current nesting level.
+    fn until_top_level_comma(&mut self) -> Result<&'a [Token]> {
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(token) = self.tokens.get(self.pos) {
+            match token.kind {
+                TokenKind::Punct('(') => depth += 1,
+                TokenKind::Punct(')') if depth == 0 => return Ok(&self.tokens[start..self.pos]),
+                TokenKind::Punct(')') => depth -= 1,
+                TokenKind::Punct(',') if depth == 0 => return Ok(&self.tokens[start..self.pos]),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Err(anyhow!("unterminated column list"))
+    }
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '-' if chars.peek().is_some_and(|&(_, c)| c == '-') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            '"' | '`' | '[' | '\'' => {
+                let close = if c == '[' { ']' } else { c };
+                loop {
+                    match chars.next() {
+                        Some((_, c)) if c == close => {
+                            // A doubled quote is an escaped quote.
+                            if close != ']' && chars.peek().is_some_and(|&(_, c)| c == close) {
+                                chars.next();
+                            } else {
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                        None => return Err(anyhow!("unterminated quote in SQL")),
+                    }
+                }
+                if c == '\'' {
+                    TokenKind::String
+                } else {
+                    TokenKind::Quoted
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                // Numbers may contain a decimal point, but names may not.
+                let is_number = c.is_ascii_digit();
+                while chars.peek().is_some_and(|&(_, c)| {
+                    c.is_alphanumeric() || c == '_' || c == '$' || is_number && c == '.'
+                }) {
+                    chars.next();
+                }
+                TokenKind::Word
+            }
+            c => TokenKind::Punct(c),
+        };
+
+        let end = chars.peek().map_or(sql.len(), |&(i, _)| i);
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multi_column() {
+        let table = parse_create_table(
+            "CREATE TABLE IF NOT EXISTS main.\"my table\" (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                [full name] VARCHAR(255) NOT NULL DEFAULT 'it''s',
+                price NUMERIC(10, 2) DEFAULT -1.5 CHECK (price > 0),
+                created_at DEFAULT (datetime('now')),
+                owner INTEGER REFERENCES owners (id) ON DELETE CASCADE,
+                CONSTRAINT pk UNIQUE (price)
+            ) WITHOUT ROWID",
+        )
+        .unwrap();
+
+        assert_eq!(table.name, "my table");
+        assert!(table.without_row_id);
+        assert_eq!(
+            table.columns,
+            vec![
+                ParsedColumn {
+                    name: "id".to_owned(),
+                    declared_type: Some("INTEGER".to_owned()),
+                    not_null: false,
+                    primary_key: true,
+                    default: None,
+                },
+                ParsedColumn {
+                    name: "full name".to_owned(),
+                    declared_type: Some("VARCHAR(255)".to_owned()),
+                    not_null: true,
+                    primary_key: false,
+                    default: Some("'it''s'".to_owned()),
+                },
+                ParsedColumn {
+                    name: "price".to_owned(),
+                    declared_type: Some("NUMERIC(10, 2)".to_owned()),
+                    not_null: false,
+                    primary_key: false,
+                    default: Some("-1.5".to_owned()),
+                },
+                ParsedColumn {
+                    name: "created_at".to_owned(),
+                    declared_type: None,
+                    not_null: false,
+                    primary_key: false,
+                    default: Some("(datetime('now'))".to_owned()),
+                },
+                ParsedColumn {
+                    name: "owner".to_owned(),
+                    declared_type: Some("INTEGER".to_owned()),
+                    not_null: false,
+                    primary_key: false,
+                    default: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_table_primary_key() {
+        let table = parse_create_table(
+            "CREATE TABLE t (a TEXT, b INT, c, PRIMARY KEY (b COLLATE nocase DESC, a))",
+        )
+        .unwrap();
+
+        let primary_key = table
+            .columns
+            .iter()
+            .map(|column| column.primary_key)
+            .collect::<Vec<_>>();
+        assert_eq!(primary_key, vec![true, true, false]);
+        assert!(!table.without_row_id);
+    }
+
+    #[test]
+    fn test_affinities() {
+        let table = parse_create_table(
+            "CREATE TABLE t (a BIGINT, b VARCHAR(10), c, d BLOB, e DOUBLE PRECISION, f DECIMAL(5), g FLOATING POINT)",
+        )
+        .unwrap();
+        assert_eq!(
+            table.affinities(),
+            [
+                Affinity::Integer,
+                Affinity::Text,
+                Affinity::Blob,
+                Affinity::Blob,
+                Affinity::Real,
+                Affinity::Numeric,
+                // "POINT" contains "INT", which takes priority.
+                Affinity::Integer,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let table = parse_create_table(
+            "CREATE TABLE t (a INTEGER DEFAULT - 3, b TEXT DEFAULT 'it''s', c REAL DEFAULT 2, d INT DEFAULT '7', e DEFAULT (1 + 1), f DEFAULT NULL, g)",
+        )
+        .unwrap();
+        assert_eq!(
+            table.defaults(),
+            [
+                SerialValue::from_i64(-3),
+                SerialValue::Text("it's".to_owned()),
+                SerialValue::from_f64(2.0),
+                SerialValue::from_i64(7),
+                SerialValue::Null,
+                SerialValue::Null,
+                SerialValue::Null,
+            ]
+        );
+        assert_eq!(table.columns[4].default_value(), None);
+    }
+}